@@ -0,0 +1,5 @@
+fn main() {
+    // Links the Node.js addon against the host's N-API symbols
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
@@ -0,0 +1,47 @@
+#ifndef TASKINATOR_H
+#define TASKINATOR_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct TcGame TcGame;
+
+// Attaches to the first running game; free it with `tc_detach`.
+struct TcGame *tc_attach(void);
+
+// Attaches to the game with the given process id; free it with `tc_detach`.
+struct TcGame *tc_attach_pid(uint32_t pid);
+
+// Closes the game's process handle and frees it.
+//
+// # Safety
+//
+// `game` must be null or a pointer returned by `tc_attach` or
+// `tc_attach_pid` that hasn't been detached.
+void tc_detach(struct TcGame *game);
+
+// Reads the current state as a JSON document; free it with `tc_free`.
+//
+// # Safety
+//
+// `game` must be null or a live pointer from `tc_attach`/`tc_attach_pid`,
+// not used from another thread at the same time.
+char *tc_poll_state_json(struct TcGame *game);
+
+// Frees a string returned by this library.
+//
+// # Safety
+//
+// `string` must be null or a string returned by this library that hasn't
+// been freed.
+void tc_free(char *string);
+
+// Describes the last failure on this thread. Valid until the next failing
+// call on the same thread; null if nothing has failed yet.
+const char *tc_last_error(void);
+
+#endif  /* TASKINATOR_H */
@@ -0,0 +1,108 @@
+// Installs and runs the capture side as a Windows service, for setups that
+// should keep working without anyone at the keyboard.
+//
+//   taskinator-service install [OPTIONS]   needs an elevated prompt
+//   taskinator-service uninstall
+//   taskinator-service run [OPTIONS]       in the foreground, for trying options out
+//
+// OPTIONS: --agent ADDR  --token TOKEN  --public  --metrics ADDR  --interval MS
+#![deny(
+    clippy::all,
+    future_incompatible,
+    nonstandard_style,
+    rust_2018_idioms,
+    warnings
+)]
+
+#[cfg(windows)]
+fn main() {
+    use std::ffi::OsString;
+
+    use taskinator_communicator::{service, watcher::StopHandle};
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, options) = match args.split_first() {
+        Some((command, options)) => (command.as_str(), options),
+        None => usage(),
+    };
+
+    let runner = || match windows::parse(options) {
+        Ok(runner) => runner,
+        Err(e) => {
+            eprintln!("{}", e);
+            usage()
+        }
+    };
+
+    let result = match command {
+        "install" => {
+            // Checked now rather than when the service first starts
+            runner();
+            let mut arguments = vec![OsString::from("service")];
+            arguments.extend(options.iter().map(OsString::from));
+            service::install(arguments).map_err(|e| e.to_string())
+        }
+        "uninstall" => service::uninstall().map_err(|e| e.to_string()),
+        "run" => runner()
+            .run(&StopHandle::default())
+            .map_err(|e| e.to_string()),
+        // What Windows launches
+        "service" => service::run_as_service(runner()).map_err(|e| e.to_string()),
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::time::Duration;
+
+    use taskinator_communicator::{redact::Redaction, service::Runner};
+
+    pub fn parse(options: &[String]) -> Result<Runner, String> {
+        let mut runner = Runner::new();
+        let mut options = options.iter();
+
+        while let Some(option) = options.next() {
+            let mut value = || {
+                options
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", option))
+            };
+
+            runner = match option.as_str() {
+                "--agent" => runner.agent(value()?.as_str()),
+                "--token" => runner.token(value()?.as_str()),
+                "--public" => runner.redaction(Redaction::public()),
+                #[cfg(feature = "prometheus")]
+                "--metrics" => runner.metrics(value()?.as_str()),
+                "--interval" => {
+                    let value = value()?;
+                    let ms = value
+                        .parse()
+                        .map_err(|_| format!("{} isn't a number", value))?;
+                    runner.interval(Duration::from_millis(ms))
+                }
+                option => return Err(format!("unknown option {}", option)),
+            };
+        }
+
+        Ok(runner)
+    }
+}
+
+#[cfg(windows)]
+fn usage() -> ! {
+    eprintln!("usage: taskinator-service install|uninstall|run [--agent ADDR] [--token TOKEN] [--public] [--metrics ADDR] [--interval MS]");
+    std::process::exit(2);
+}
+
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("taskinator-service only runs on Windows");
+    std::process::exit(1);
+}
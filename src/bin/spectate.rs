@@ -0,0 +1,536 @@
+// A live dashboard in the terminal: who's in the game, how far the tasks have
+// got, where the meeting is up to and what's changed. Anything the crate gets
+// wrong about a new game version tends to show up here first.
+//
+//   taskinator-spectate [--pid PID] [--helper] [--interval MS]
+//   taskinator-spectate --fake SEED
+//
+// q or Esc quits.
+#![deny(
+    clippy::all,
+    future_incompatible,
+    nonstandard_style,
+    rust_2018_idioms,
+    warnings
+)]
+
+use std::{
+    collections::VecDeque,
+    io,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Gauge, List, ListItem, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use taskinator_communicator::{
+    events::{self, Event},
+    fake::FakeGame,
+    metrics,
+    prelude::*,
+};
+
+// Entries kept in the event log
+const LOG_LEN: usize = 200;
+
+struct Options {
+    pid: Option<usize>,
+    fake: Option<u64>,
+    helper: bool,
+    interval: Duration,
+}
+
+fn main() {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: taskinator-spectate [--pid PID] [--helper] [--interval MS] [--fake SEED]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let spectator = match Spectator::open(&options) {
+        Ok(spectator) => spectator,
+        Err(e) => {
+            eprintln!("couldn't attach: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let terminal = ratatui::init();
+    let result = run(terminal, spectator, options.interval);
+    ratatui::restore();
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse(mut args: impl Iterator<Item = String>) -> std::result::Result<Options, String> {
+    let mut options = Options {
+        pid: None,
+        fake: None,
+        helper: false,
+        interval: Duration::from_millis(100),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+
+        match arg.as_str() {
+            "--pid" => options.pid = Some(number(&value("--pid")?)?),
+            "--fake" => options.fake = Some(number(&value("--fake")?)?),
+            "--interval" => {
+                options.interval = Duration::from_millis(number(&value("--interval")?)?)
+            }
+            "--helper" => options.helper = true,
+            arg => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> std::result::Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} isn't a number", value))
+}
+
+// Seconds remaining, when a helper is loaded to say
+#[derive(Default)]
+struct Countdowns {
+    meeting: Option<f32>,
+    kill_cooldown: Option<f32>,
+    emergency_cooldown: Option<f32>,
+}
+
+struct Spectator {
+    source: Box<dyn StateSource>,
+    #[cfg(all(windows, feature = "helper"))]
+    helper: Option<taskinator_communicator::helper::HelperGame>,
+}
+
+impl Spectator {
+    fn open(options: &Options) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        if let Some(seed) = options.fake {
+            return Ok(Spectator {
+                source: Box::new(FakeGame::random(seed)),
+                #[cfg(all(windows, feature = "helper"))]
+                helper: None,
+            });
+        }
+
+        Self::attach(options)
+    }
+
+    #[cfg(windows)]
+    fn attach(options: &Options) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let game = match options.pid {
+            Some(pid) => Game::from_pid(pid)?,
+            None => Game::find()?,
+        };
+
+        #[cfg(feature = "helper")]
+        let helper = if options.helper {
+            Some(taskinator_communicator::helper::HelperGame::connect(
+                game.pid(),
+            )?)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "helper"))]
+        if options.helper {
+            eprintln!("built without the helper feature, so there are no countdowns");
+        }
+
+        Ok(Spectator {
+            source: Box::new(game),
+            #[cfg(feature = "helper")]
+            helper,
+        })
+    }
+
+    #[cfg(not(windows))]
+    fn attach(_: &Options) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        Err("attaching to the game needs Windows; --fake SEED runs against a made up one".into())
+    }
+
+    fn countdowns(&self) -> Option<Countdowns> {
+        #[cfg(all(windows, feature = "helper"))]
+        if let Some(helper) = &self.helper {
+            return match helper.timers() {
+                Ok(timers) => Some(Countdowns {
+                    meeting: timers.meeting,
+                    kill_cooldown: timers.kill_cooldown,
+                    emergency_cooldown: timers.emergency_cooldown,
+                }),
+                Err(_) => Some(Countdowns::default()),
+            };
+        }
+
+        None
+    }
+}
+
+struct Dashboard {
+    started: Instant,
+    state: Option<State>,
+    // When the phase or meeting state last changed
+    phase_since: Instant,
+    countdowns: Option<Countdowns>,
+    log: VecDeque<(Duration, String)>,
+    error: Option<String>,
+}
+
+fn run(mut terminal: DefaultTerminal, spectator: Spectator, interval: Duration) -> io::Result<()> {
+    let now = Instant::now();
+    let mut dashboard = Dashboard {
+        started: now,
+        state: None,
+        phase_since: now,
+        countdowns: None,
+        log: VecDeque::new(),
+        error: None,
+    };
+
+    loop {
+        match spectator.source.state() {
+            Ok(state) => {
+                dashboard.update(state);
+                dashboard.error = None;
+            }
+            Err(e) => dashboard.error = Some(e.to_string()),
+        }
+        dashboard.countdowns = spectator.countdowns();
+
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        if event::poll(interval)? {
+            if let TermEvent::Key(key) = event::read()? {
+                let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    || (key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL));
+
+                if key.kind == KeyEventKind::Press && quit {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+impl Dashboard {
+    fn update(&mut self, state: State) {
+        if let Some(last) = &self.state {
+            if last == &state {
+                return;
+            }
+
+            let mut lines = transitions(last, &state);
+            lines.extend(
+                events::diff(last, &state)
+                    .iter()
+                    .map(|e| describe(&state, e)),
+            );
+
+            if phase(last) != phase(&state) {
+                self.phase_since = Instant::now();
+            }
+
+            let elapsed = self.started.elapsed();
+            for line in lines {
+                if self.log.len() == LOG_LEN {
+                    self.log.pop_front();
+                }
+                self.log.push_back((elapsed, line));
+            }
+        }
+
+        self.state = Some(state);
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>) {
+        let [header, tasks, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [players, log] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(body);
+
+        frame.render_widget(
+            Paragraph::new(self.header()).block(Block::default().borders(Borders::ALL)),
+            header,
+        );
+        frame.render_widget(self.task_bar(), tasks);
+        frame.render_widget(self.player_table(), players);
+        frame.render_widget(self.event_log(), log);
+
+        let footer_text = match &self.error {
+            Some(e) => Line::styled(
+                format!("read failed: {}", e),
+                Style::default().fg(Color::Red),
+            ),
+            None => Line::styled("q to quit", Style::default().fg(Color::DarkGray)),
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+    fn header(&self) -> Line<'_> {
+        let in_phase = clock(self.phase_since.elapsed());
+
+        let mut spans = match &self.state {
+            None => vec![Span::raw("waiting for the first read")],
+            Some(State::Menu) => vec![Span::raw(format!("menu for {}", in_phase))],
+            Some(State::Lobby { players }) => vec![Span::raw(format!(
+                "lobby, {} players, for {}",
+                players.len(),
+                in_phase
+            ))],
+            Some(State::InGame { meeting, .. }) => {
+                let meeting = match meeting {
+                    MeetingState::Proceeding => "no meeting".to_string(),
+                    meeting => format!("meeting: {}", meeting),
+                };
+                vec![
+                    Span::styled("in game", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(", {} for {}", meeting, in_phase)),
+                ]
+            }
+            Some(State::Ended { .. }) => vec![
+                Span::styled("game over", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" for {}", in_phase)),
+            ],
+        };
+
+        if let Some(countdowns) = &self.countdowns {
+            for (label, seconds) in [
+                ("meeting", countdowns.meeting),
+                ("kill", countdowns.kill_cooldown),
+                ("button", countdowns.emergency_cooldown),
+            ] {
+                if let Some(seconds) = seconds {
+                    spans.push(Span::styled(
+                        format!("  {} {:.0}s", label, seconds.max(0.0)),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+            }
+        }
+
+        Line::from(spans)
+    }
+
+    fn task_bar(&self) -> Gauge<'_> {
+        let block = Block::default().borders(Borders::ALL).title("Tasks");
+
+        match &self.state {
+            Some(
+                state @ State::InGame {
+                    tasks_completed,
+                    tasks_total,
+                    ..
+                },
+            ) => Gauge::default()
+                .block(block)
+                .gauge_style(Style::default().fg(Color::Green))
+                .ratio(f64::from(metrics::metrics(state).task_progress.unwrap_or(0.0)).min(1.0))
+                .label(format!("{}/{}", tasks_completed, tasks_total)),
+            _ => Gauge::default().block(block).ratio(0.0).label("-"),
+        }
+    }
+
+    fn player_table(&self) -> Table<'_> {
+        let players = self.state.as_ref().map_or(&[][..], State::players);
+
+        let rows = players.iter().map(|player| {
+            let colour = player
+                .colour
+                .as_rgb()
+                .map_or(Color::Reset, |(r, g, b)| Color::Rgb(r, g, b));
+            let status = if player.disconnected {
+                "left"
+            } else if player.dead {
+                "dead"
+            } else {
+                "alive"
+            };
+            let style = if player.dead || player.disconnected {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+
+            Row::new(vec![
+                Cell::from(player.id.to_string()),
+                Cell::from(Span::styled("■", Style::default().fg(colour))),
+                Cell::from(player.name.clone()),
+                Cell::from(status),
+                Cell::from(if player.impostor { "impostor" } else { "" })
+                    .style(Style::default().fg(Color::Red)),
+            ])
+            .style(style)
+        });
+
+        Table::new(
+            rows,
+            [
+                Constraint::Length(3),
+                Constraint::Length(2),
+                Constraint::Min(12),
+                Constraint::Length(6),
+                Constraint::Length(9),
+            ],
+        )
+        .header(
+            Row::new(["id", "", "name", "status", "role"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Players"))
+    }
+
+    fn event_log(&self) -> List<'_> {
+        let items = self.log.iter().rev().map(|(elapsed, line)| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", clock(*elapsed)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(line.as_str()),
+            ]))
+        });
+
+        List::new(items).block(Block::default().borders(Borders::ALL).title("Events"))
+    }
+}
+
+// Menu, lobby or game, and where the meeting is up to
+fn phase(state: &State) -> (Phase, Option<MeetingState>) {
+    match state {
+        State::InGame { meeting, .. } => (Phase::InGame, Some(*meeting)),
+        state => (state.phase(), None),
+    }
+}
+
+// What events::diff doesn't report: the phase changing and players joining,
+// leaving and dying
+fn transitions(old: &State, new: &State) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    match (old, new) {
+        (State::InGame { .. }, State::InGame { .. }) => {}
+        (_, State::Menu) => lines.push("back to the menu".to_string()),
+        (State::Menu, State::Lobby { .. }) => lines.push("joined a lobby".to_string()),
+        (_, State::Ended { players }) => {
+            let impostors: Vec<&str> = players
+                .iter()
+                .filter(|p| p.impostor)
+                .map(|p| p.name.as_str())
+                .collect();
+            lines.push(format!("game over; impostors: {}", impostors.join(", ")));
+        }
+        (State::Ended { .. }, State::Lobby { .. }) => lines.push("back in the lobby".to_string()),
+        (State::InGame { .. }, State::Lobby { .. }) => lines.push("game over".to_string()),
+        (_, State::InGame { .. }) => lines.push("game started".to_string()),
+        _ => {}
+    }
+
+    if let (
+        State::InGame {
+            meeting: old_meeting,
+            ..
+        },
+        State::InGame { meeting, .. },
+    ) = (old, new)
+    {
+        if old_meeting != meeting {
+            lines.push(match meeting {
+                MeetingState::Animating => "meeting called".to_string(),
+                MeetingState::Proceeding => "meeting over".to_string(),
+                meeting => format!("meeting: {}", meeting),
+            });
+        }
+    }
+
+    let same_phase = matches!(
+        (old, new),
+        (State::Lobby { .. }, State::Lobby { .. }) | (State::InGame { .. }, State::InGame { .. })
+    );
+    if !same_phase {
+        return lines;
+    }
+
+    for player in new.players() {
+        match old.players().iter().find(|p| p.id == player.id) {
+            None => lines.push(format!("{} joined", player.name)),
+            Some(previous) => {
+                if player.dead && !previous.dead {
+                    lines.push(format!("{} died", player.name));
+                }
+                if player.disconnected && !previous.disconnected {
+                    lines.push(format!("{} left", player.name));
+                }
+            }
+        }
+    }
+    for player in old.players() {
+        if !new.players().iter().any(|p| p.id == player.id) {
+            lines.push(format!("{} left", player.name));
+        }
+    }
+
+    lines
+}
+
+fn describe(state: &State, event: &Event) -> String {
+    let name = |id: &u8| {
+        state
+            .players()
+            .iter()
+            .find(|p| p.id == *id)
+            .map_or_else(|| format!("player {}", id), |p| p.name.clone())
+    };
+    let cosmetic =
+        |name: Option<&str>, id: u32| name.map_or_else(|| format!("#{}", id), String::from);
+
+    match event {
+        Event::NameChanged { old, new, .. } => format!("{} is now {}", old, new),
+        Event::ColourChanged { id, new, .. } => format!(
+            "{} changed colour to {}",
+            name(id),
+            new.name()
+                .map_or_else(|| format!("#{}", new.id()), String::from)
+        ),
+        Event::HatChanged { id, new, .. } => {
+            format!("{} put on {}", name(id), cosmetic(new.name(), new.id()))
+        }
+        Event::PetChanged { id, new, .. } => {
+            format!("{} brought {}", name(id), cosmetic(new.name(), new.id()))
+        }
+        Event::SkinChanged { id, new, .. } => {
+            format!(
+                "{} changed into {}",
+                name(id),
+                cosmetic(new.name(), new.id())
+            )
+        }
+    }
+}
+
+fn clock(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,186 @@
+// One watcher shared by every part of an app that wants the game, so they
+// don't each poll the process. Subscribers come and go while it runs; each
+// gets its own channel or callback and its own filter.
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+
+use crate::{
+    error::Error,
+    events::Event,
+    redact::Redaction,
+    state::State,
+    watcher::{GameWatcher, StopHandle},
+};
+
+// Per subscriber, so a subscriber that falls behind only loses its own updates
+const SUBSCRIBER_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum Update {
+    State(State),
+    Event(Event),
+}
+
+type EventPredicate = Box<dyn Fn(&Event) -> bool + Send>;
+
+// By default a subscriber gets every event and no states
+#[derive(Default)]
+pub struct Filter {
+    states: bool,
+    events: Option<EventPredicate>,
+    redaction: Redaction,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Also send every state read, whether or not anything changed
+    pub fn states(mut self, enabled: bool) -> Self {
+        self.states = enabled;
+        self
+    }
+
+    // Only send the events this returns true for
+    pub fn events(mut self, predicate: impl Fn(&Event) -> bool + Send + 'static) -> Self {
+        self.events = Some(Box::new(predicate));
+        self
+    }
+
+    // Applied to the states sent
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+}
+
+enum Sink {
+    Channel(Sender<Update>),
+    Callback(Box<dyn FnMut(&Update) -> bool + Send>),
+}
+
+struct Subscriber {
+    filter: Filter,
+    sink: Sink,
+}
+
+impl Subscriber {
+    // false once the subscriber has gone
+    fn deliver(&mut self, state: &State, events: &[Event]) -> bool {
+        if self.filter.states && !self.send(Update::State(self.filter.redaction.redacted(state))) {
+            return false;
+        }
+
+        for event in events {
+            if let Some(predicate) = &self.filter.events {
+                if !predicate(event) {
+                    continue;
+                }
+            }
+
+            if !self.send(Update::Event(event.clone())) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn send(&mut self, update: Update) -> bool {
+        match &mut self.sink {
+            Sink::Channel(sender) => match sender.try_send(update) {
+                Ok(()) => true,
+                Err(TrySendError::Full(update)) => {
+                    tracing::trace!("subscriber channel full, dropping {:?}", update);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+            Sink::Callback(callback) => callback(&update),
+        }
+    }
+}
+
+// A handle to the running broker; clones share it
+#[derive(Clone)]
+pub struct Broker {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    stop: StopHandle,
+}
+
+impl Broker {
+    // Polls on a thread of its own until stopped or until an error it can't
+    // recover from, whether or not anyone is subscribed
+    pub fn spawn(watcher: GameWatcher, interval: Duration) -> (JoinHandle<()>, Broker) {
+        let broker = Broker {
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            stop: watcher.stop_handle(),
+        };
+
+        let shared = broker.clone();
+        let mut watcher = watcher;
+        let handle = thread::spawn(move || loop {
+            match watcher.poll() {
+                Ok((state, events)) => shared
+                    .lock()
+                    .retain_mut(|subscriber| subscriber.deliver(&state, &events)),
+                Err(Error::Cancelled) => break,
+                Err(e) if e.is_fatal() => {
+                    tracing::warn!("broker thread stopping: {}", e);
+                    break;
+                }
+                Err(e) => tracing::debug!("poll failed: {}", e),
+            }
+
+            thread::sleep(interval);
+        });
+
+        (handle, broker)
+    }
+
+    // Unsubscribes when the receiver is dropped
+    pub fn subscribe(&self, filter: Filter) -> Receiver<Update> {
+        let (sender, receiver) = crossbeam_channel::bounded(SUBSCRIBER_CAPACITY);
+        self.lock().push(Subscriber {
+            filter,
+            sink: Sink::Channel(sender),
+        });
+
+        receiver
+    }
+
+    // Called on the broker's thread, so it holds up every other subscriber
+    // while it runs; returning false unsubscribes it
+    pub fn subscribe_with(
+        &self,
+        filter: Filter,
+        callback: impl FnMut(&Update) -> bool + Send + 'static,
+    ) {
+        self.lock().push(Subscriber {
+            filter,
+            sink: Sink::Callback(Box::new(callback)),
+        });
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Subscriber>> {
+        // A callback that panicked took the broker thread with it; what's
+        // left is still consistent
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
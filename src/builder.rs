@@ -0,0 +1,135 @@
+use crate::{
+    game::{
+        with_access_fallback, AccessMode, CacheSettings, Consistency, Game, ModuleMatch,
+        StateReadOptions, StringDecoding,
+    },
+    offsets::OffsetTable,
+    packs::OffsetPack,
+    retry::RetryPolicy,
+    Result,
+};
+
+// Collects everything needed to attach and configure a Game in one place
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+    // None finds the first running game
+    pid: Option<usize>,
+    // None tries full access, then limited access like Game::find
+    access: Option<AccessMode>,
+    module: ModuleMatch,
+    // None keeps what the game picked for its version
+    offsets: Option<OffsetTable>,
+    retry_policy: RetryPolicy,
+    read_options: StateReadOptions,
+    cache_settings: CacheSettings,
+    string_decoding: StringDecoding,
+    consistency: Consistency,
+    player_threads: usize,
+    offset_packs: Vec<OffsetPack>,
+}
+
+impl GameBuilder {
+    pub fn new() -> Self {
+        GameBuilder {
+            pid: None,
+            access: None,
+            module: ModuleMatch::default(),
+            offsets: None,
+            retry_policy: RetryPolicy::default(),
+            read_options: StateReadOptions::default(),
+            cache_settings: CacheSettings::default(),
+            string_decoding: StringDecoding::Strict,
+            consistency: Consistency::default(),
+            player_threads: 1,
+            offset_packs: Vec::new(),
+        }
+    }
+
+    pub fn pid(mut self, pid: usize) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    pub fn access(mut self, access: AccessMode) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    // How to recognise GameAssembly.dll, for copies that rename it
+    pub fn module(mut self, module: ModuleMatch) -> Self {
+        self.module = module;
+        self
+    }
+
+    pub fn offsets(mut self, offsets: OffsetTable) -> Self {
+        self.offsets = Some(offsets);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn read_options(mut self, read_options: StateReadOptions) -> Self {
+        self.read_options = read_options;
+        self
+    }
+
+    pub fn cache_settings(mut self, cache_settings: CacheSettings) -> Self {
+        self.cache_settings = cache_settings;
+        self
+    }
+
+    pub fn string_decoding(mut self, string_decoding: StringDecoding) -> Self {
+        self.string_decoding = string_decoding;
+        self
+    }
+
+    pub fn consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = consistency;
+        self
+    }
+
+    pub fn player_threads(mut self, threads: usize) -> Self {
+        self.player_threads = threads;
+        self
+    }
+
+    pub fn offset_pack(mut self, pack: OffsetPack) -> Self {
+        self.offset_packs.push(pack);
+        self
+    }
+
+    pub fn build(self) -> Result<Game> {
+        let attach = |access| match self.pid {
+            Some(pid) => Game::from_pid_with_module(pid, access, self.module.clone()),
+            None => Game::find_with_module(access, &self.module),
+        };
+        let mut game = match self.access {
+            Some(access) => attach(access)?,
+            None => with_access_fallback(attach)?,
+        };
+
+        if let Some(offsets) = self.offsets {
+            game.set_offsets(offsets);
+        }
+        game.set_retry_policy(self.retry_policy);
+        game.set_read_options(self.read_options);
+        game.set_cache_settings(self.cache_settings);
+        game.set_string_decoding(self.string_decoding);
+        game.set_consistency(self.consistency);
+        game.set_player_threads(self.player_threads);
+        for pack in self.offset_packs {
+            game.add_offset_pack(pack);
+        }
+
+        Ok(game)
+    }
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
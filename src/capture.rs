@@ -0,0 +1,477 @@
+// Watches the game's traffic with pcap, either to corroborate what the memory
+// reader sees or to stand in for it when a patch has moved the offsets but
+// left the protocol alone
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use pcap::{Active, Capture, Device, Linktype};
+
+use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    hazel::{self, Language, NetEvent, RpcCall},
+    state::State,
+};
+
+// The official servers' game ports
+pub const DEFAULT_FILTER: &str = "udp portrange 22023-22923";
+
+pub struct NetCapture {
+    capture: Capture<Active>,
+    linktype: Linktype,
+}
+
+impl NetCapture {
+    // Opens the given device, or the default one, filtered to game traffic
+    pub fn open(device: Option<&str>) -> Result<Self, pcap::Error> {
+        let device = match device {
+            Some(name) => Device::from(name),
+            None => Device::lookup()?.ok_or(pcap::Error::PcapError(
+                "no capture device found".to_string(),
+            ))?,
+        };
+
+        let mut capture = Capture::from_device(device)?.immediate_mode(true).open()?;
+        capture.filter(DEFAULT_FILTER, true)?;
+
+        let linktype = capture.get_datalink();
+
+        Ok(NetCapture { capture, linktype })
+    }
+
+    pub fn set_filter(&mut self, filter: &str) -> Result<(), pcap::Error> {
+        self.capture.filter(filter, true)
+    }
+
+    // Blocks until the next packet arrives; packets that aren't UDP come back
+    // with no events
+    pub fn next_events(&mut self) -> Result<Vec<NetEvent>, pcap::Error> {
+        let packet = self.capture.next_packet()?;
+
+        Ok(udp_payload(self.linktype, packet.data)
+            .map(hazel::decode_datagram)
+            .unwrap_or_default())
+    }
+}
+
+fn udp_payload(linktype: Linktype, frame: &[u8]) -> Option<&[u8]> {
+    let ip = match linktype {
+        Linktype::ETHERNET => {
+            let mut ip = frame.get(14..)?;
+            // 802.1Q tag
+            if frame.get(12..14)? == [0x81, 0x00] {
+                ip = frame.get(18..)?;
+            }
+            ip
+        }
+        // Loopback on Windows, with a four byte address family
+        Linktype::NULL | Linktype::LOOP => frame.get(4..)?,
+        Linktype::LINUX_SLL => frame.get(16..)?,
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => frame,
+        _ => return None,
+    };
+
+    let udp = match ip.first()? >> 4 {
+        4 if *ip.get(9)? == 17 => ip.get(usize::from(ip[0] & 0x0F) * 4..)?,
+        6 if *ip.get(6)? == 17 => ip.get(40..)?,
+        _ => return None,
+    };
+
+    let len = usize::from(u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]));
+    udp.get(8..len.max(8))
+}
+
+// What the traffic says about a PlayerControl, keyed by its net id
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetPlayer {
+    pub net_id: u32,
+    // Once its spawn has been seen
+    pub player_id: Option<u8>,
+    pub name: Option<String>,
+    pub colour: Option<Colour>,
+    pub hat: Option<Hat>,
+    pub skin: Option<Skin>,
+    pub pet: Option<Pet>,
+    pub dead: bool,
+    // Last known, in world units
+    pub position: Option<(f32, f32)>,
+    // Since the game started
+    pub tasks_completed: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    // Named on the network but not found in memory
+    Missing {
+        name: String,
+    },
+    Colour {
+        name: String,
+        memory: Colour,
+        network: Colour,
+    },
+    Dead {
+        name: String,
+        memory: bool,
+        network: bool,
+    },
+}
+
+// Client ids of the host who left and the one who took over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostMigrated {
+    pub old: i32,
+    pub new: i32,
+}
+
+// Folds network events into a picture of the players
+#[derive(Debug, Clone, Default)]
+pub struct NetPlayers {
+    players: BTreeMap<u32, NetPlayer>,
+    // Voters' client ids by the client id they voted to kick
+    kick_votes: BTreeMap<i32, BTreeSet<i32>>,
+    // Our own client id
+    client_id: Option<i32>,
+    host_id: Option<i32>,
+    language: Option<Language>,
+    max_players: Option<u8>,
+    // PlayerControl net ids by their CustomNetworkTransform's
+    transforms: BTreeMap<u32, u32>,
+    // Net ids of each client's player objects, so they go when the client
+    // does
+    owners: BTreeMap<i32, Vec<u32>>,
+    // Between a meeting being called and the votes coming in
+    in_meeting: bool,
+    // Tasks handed out this game, by player id
+    task_totals: BTreeMap<u8, u32>,
+}
+
+impl NetPlayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Some when the event handed the lobby to a new host
+    pub fn apply(&mut self, event: &NetEvent) -> Option<HostMigrated> {
+        match event {
+            NetEvent::JoinedGame {
+                client_id, host_id, ..
+            } => {
+                self.players.clear();
+                self.kick_votes.clear();
+                self.transforms.clear();
+                self.owners.clear();
+                self.task_totals.clear();
+                self.in_meeting = false;
+                self.client_id = Some(*client_id);
+                self.host_id = Some(*host_id);
+                self.language = None;
+                self.max_players = None;
+            }
+            NetEvent::PlayerLeft {
+                client_id, host_id, ..
+            } => {
+                self.forget_client(*client_id);
+
+                let old = self.host_id.replace(*host_id);
+                if let Some(old) = old.filter(|old| old != host_id) {
+                    return Some(HostMigrated { old, new: *host_id });
+                }
+            }
+            NetEvent::PlayerKicked { client_id, .. } => {
+                self.forget_client(*client_id);
+            }
+            NetEvent::PlayerSpawned {
+                owner_client_id,
+                player_id,
+                net_ids,
+            } => {
+                if let [control, _, transform, ..] = net_ids[..] {
+                    self.transforms.insert(transform, control);
+                    self.player(control).player_id = Some(*player_id);
+                }
+                self.owners
+                    .entry(*owner_client_id)
+                    .or_default()
+                    .extend(net_ids);
+            }
+            NetEvent::Despawned { net_id } => {
+                self.forget_object(*net_id);
+                for owned in self.owners.values_mut() {
+                    owned.retain(|owned| owned != net_id);
+                }
+            }
+            NetEvent::Data { net_id, data } => {
+                if let Some(&control) = self.transforms.get(net_id) {
+                    if let Some(position) = hazel::transform_position(data) {
+                        self.player(control).position = Some(position);
+                    }
+                }
+            }
+            NetEvent::Rpc {
+                call: RpcCall::AddKickVote { voter, target },
+                ..
+            } => {
+                self.kick_votes.entry(*target).or_default().insert(*voter);
+            }
+            NetEvent::Rpc {
+                call:
+                    RpcCall::SyncSettings {
+                        max_players,
+                        language,
+                    },
+                ..
+            } => {
+                self.max_players = Some(*max_players);
+                self.language = Some(*language);
+            }
+            NetEvent::Rpc {
+                call:
+                    RpcCall::SetTasks {
+                        player_id,
+                        task_types,
+                    },
+                ..
+            } => {
+                self.task_totals.insert(*player_id, task_types.len() as u32);
+            }
+            NetEvent::Rpc {
+                call: RpcCall::StartMeeting { .. },
+                ..
+            } => self.in_meeting = true,
+            NetEvent::Rpc {
+                call: RpcCall::VotingComplete,
+                ..
+            } => self.in_meeting = false,
+            NetEvent::GameStarted { .. } | NetEvent::GameEnded { .. } => {
+                self.task_totals.clear();
+                self.in_meeting = false;
+                for player in self.players.values_mut() {
+                    player.dead = false;
+                    player.tasks_completed = 0;
+                }
+            }
+            NetEvent::Rpc { net_id, call } => {
+                let net_id = match call {
+                    RpcCall::MurderPlayer { target_net_id } => *target_net_id,
+                    _ => *net_id,
+                };
+                // RPCs also go to the MeetingHud, ShipStatus and the like;
+                // only players whose spawn has been seen are tracked
+                if let Some(player) = self.players.get_mut(&net_id) {
+                    match call {
+                        RpcCall::CompleteTask(_) => player.tasks_completed += 1,
+                        RpcCall::SetName(name) => player.name = Some(name.clone()),
+                        RpcCall::SetColour(colour) => player.colour = Some(*colour),
+                        RpcCall::SetHat(hat) => player.hat = Some(*hat),
+                        RpcCall::SetSkin(skin) => player.skin = Some(*skin),
+                        RpcCall::SetPet(pet) => player.pet = Some(*pet),
+                        RpcCall::MurderPlayer { .. } => player.dead = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // Client id of the lobby's host, once we've seen ourselves join
+    pub fn host_id(&self) -> Option<i32> {
+        self.host_id
+    }
+
+    // Whether we're the one who can start the game
+    pub fn is_host(&self) -> Option<bool> {
+        Some(self.client_id? == self.host_id?)
+    }
+
+    // As last set by the host
+    pub fn language(&self) -> Option<Language> {
+        self.language
+    }
+
+    // As last set by the host
+    pub fn max_players(&self) -> Option<u8> {
+        self.max_players
+    }
+
+    // Completed and total tasks by player id, for players whose spawn and
+    // task list have both been seen
+    pub fn task_progress(&self) -> HashMap<u8, (u32, u32)> {
+        self.players()
+            .filter_map(|player| {
+                let player_id = player.player_id?;
+                let total = *self.task_totals.get(&player_id)?;
+                Some((player_id, (player.tasks_completed, total)))
+            })
+            .collect()
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &NetPlayer> {
+        self.players.values()
+    }
+
+    // Nobody can move or do tasks while a meeting's on
+    pub fn in_meeting(&self) -> bool {
+        self.in_meeting
+    }
+
+    // Votes to kick the given client that are still standing; a voter who
+    // leaves takes their vote with them
+    pub fn kick_votes(&self, client_id: i32) -> usize {
+        self.kick_votes.get(&client_id).map_or(0, BTreeSet::len)
+    }
+
+    // Players are matched by name, since net ids never appear in memory;
+    // anyone not yet named on the network is left out
+    pub fn cross_check(&self, state: &State) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+
+        for net in self.players() {
+            let name = match &net.name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let memory = match state.players().iter().find(|p| &p.name == name) {
+                Some(memory) => memory,
+                None => {
+                    mismatches.push(Mismatch::Missing { name: name.clone() });
+                    continue;
+                }
+            };
+
+            if let Some(colour) = net.colour {
+                if colour != memory.colour {
+                    mismatches.push(Mismatch::Colour {
+                        name: name.clone(),
+                        memory: memory.colour,
+                        network: colour,
+                    });
+                }
+            }
+
+            if let State::InGame { .. } = state {
+                if net.dead != memory.dead {
+                    mismatches.push(Mismatch::Dead {
+                        name: name.clone(),
+                        memory: memory.dead,
+                        network: net.dead,
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    fn player(&mut self, net_id: u32) -> &mut NetPlayer {
+        self.players.entry(net_id).or_insert_with(|| NetPlayer {
+            net_id,
+            ..NetPlayer::default()
+        })
+    }
+
+    fn forget_client(&mut self, client_id: i32) {
+        self.kick_votes.remove(&client_id);
+        for voters in self.kick_votes.values_mut() {
+            voters.remove(&client_id);
+        }
+
+        for net_id in self.owners.remove(&client_id).unwrap_or_default() {
+            self.forget_object(net_id);
+        }
+    }
+
+    fn forget_object(&mut self, net_id: u32) {
+        self.players.remove(&net_id);
+        self.transforms
+            .retain(|&transform, &mut control| transform != net_id && control != net_id);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerIdle {
+    pub net_id: u32,
+    pub name: Option<String>,
+    pub idle_for: Duration,
+}
+
+// Flags players who have neither moved nor completed a task for the threshold;
+// each stretch of idleness is reported once, when it crosses the threshold
+#[derive(Debug, Clone)]
+pub struct IdleDetector {
+    threshold: Duration,
+    // What each player was last seen doing, and since when
+    activity: BTreeMap<u32, Activity>,
+}
+
+#[derive(Debug, Clone)]
+struct Activity {
+    position: Option<(f32, f32)>,
+    tasks_completed: u32,
+    since: Instant,
+    reported: bool,
+}
+
+impl IdleDetector {
+    pub fn new(threshold: Duration) -> Self {
+        IdleDetector {
+            threshold,
+            activity: BTreeMap::new(),
+        }
+    }
+
+    // Call after applying each batch of events, or on a timer
+    pub fn check(&mut self, players: &NetPlayers, now: Instant) -> Vec<PlayerIdle> {
+        let mut idle = Vec::new();
+
+        // Everyone starts afresh once the meeting's over
+        if players.in_meeting() {
+            self.activity.clear();
+            return idle;
+        }
+
+        self.activity
+            .retain(|net_id, _| players.players.contains_key(net_id));
+
+        for player in players.players() {
+            let activity = self
+                .activity
+                .entry(player.net_id)
+                .or_insert_with(|| Activity {
+                    position: player.position,
+                    tasks_completed: player.tasks_completed,
+                    since: now,
+                    reported: false,
+                });
+
+            if activity.position != player.position
+                || activity.tasks_completed != player.tasks_completed
+            {
+                *activity = Activity {
+                    position: player.position,
+                    tasks_completed: player.tasks_completed,
+                    since: now,
+                    reported: false,
+                };
+                continue;
+            }
+
+            let idle_for = now.saturating_duration_since(activity.since);
+            if idle_for >= self.threshold && !activity.reported {
+                activity.reported = true;
+                idle.push(PlayerIdle {
+                    net_id: player.net_id,
+                    name: player.name.clone(),
+                    idle_for,
+                });
+            }
+        }
+
+        idle
+    }
+}
@@ -0,0 +1,124 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Colour {
+    Red,
+    Blue,
+    Green,
+    Pink,
+    Orange,
+    Yellow,
+    Black,
+    White,
+    Purple,
+    Brown,
+    Cyan,
+    Lime,
+    Maroon,
+    Rose,
+    Banana,
+    Grey,
+    Tan,
+    Coral,
+    Unknown(i32),
+}
+
+impl Colour {
+    pub fn id(self) -> i32 {
+        match self {
+            Colour::Red => 0,
+            Colour::Blue => 1,
+            Colour::Green => 2,
+            Colour::Pink => 3,
+            Colour::Orange => 4,
+            Colour::Yellow => 5,
+            Colour::Black => 6,
+            Colour::White => 7,
+            Colour::Purple => 8,
+            Colour::Brown => 9,
+            Colour::Cyan => 10,
+            Colour::Lime => 11,
+            Colour::Maroon => 12,
+            Colour::Rose => 13,
+            Colour::Banana => 14,
+            Colour::Grey => 15,
+            Colour::Tan => 16,
+            Colour::Coral => 17,
+            Colour::Unknown(id) => id,
+        }
+    }
+
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Colour::Red => Some("Red"),
+            Colour::Blue => Some("Blue"),
+            Colour::Green => Some("Green"),
+            Colour::Pink => Some("Pink"),
+            Colour::Orange => Some("Orange"),
+            Colour::Yellow => Some("Yellow"),
+            Colour::Black => Some("Black"),
+            Colour::White => Some("White"),
+            Colour::Purple => Some("Purple"),
+            Colour::Brown => Some("Brown"),
+            Colour::Cyan => Some("Cyan"),
+            Colour::Lime => Some("Lime"),
+            Colour::Maroon => Some("Maroon"),
+            Colour::Rose => Some("Rose"),
+            Colour::Banana => Some("Banana"),
+            Colour::Grey => Some("Grey"),
+            Colour::Tan => Some("Tan"),
+            Colour::Coral => Some("Coral"),
+            Colour::Unknown(_) => None,
+        }
+    }
+
+    // Body colours from the game's palette
+    pub fn as_rgb(self) -> Option<(u8, u8, u8)> {
+        match self {
+            Colour::Red => Some((198, 17, 17)),
+            Colour::Blue => Some((19, 46, 210)),
+            Colour::Green => Some((17, 128, 45)),
+            Colour::Pink => Some((238, 84, 187)),
+            Colour::Orange => Some((240, 125, 13)),
+            Colour::Yellow => Some((246, 246, 87)),
+            Colour::Black => Some((63, 71, 78)),
+            Colour::White => Some((215, 225, 241)),
+            Colour::Purple => Some((107, 47, 188)),
+            Colour::Brown => Some((113, 73, 30)),
+            Colour::Cyan => Some((56, 255, 221)),
+            Colour::Lime => Some((80, 240, 57)),
+            Colour::Maroon => Some((95, 29, 46)),
+            Colour::Rose => Some((236, 192, 211)),
+            Colour::Banana => Some((240, 231, 168)),
+            Colour::Grey => Some((117, 133, 147)),
+            Colour::Tan => Some((145, 136, 119)),
+            Colour::Coral => Some((215, 100, 100)),
+            Colour::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<i32> for Colour {
+    fn from(id: i32) -> Self {
+        match id {
+            0 => Colour::Red,
+            1 => Colour::Blue,
+            2 => Colour::Green,
+            3 => Colour::Pink,
+            4 => Colour::Orange,
+            5 => Colour::Yellow,
+            6 => Colour::Black,
+            7 => Colour::White,
+            8 => Colour::Purple,
+            9 => Colour::Brown,
+            10 => Colour::Cyan,
+            11 => Colour::Lime,
+            12 => Colour::Maroon,
+            13 => Colour::Rose,
+            14 => Colour::Banana,
+            15 => Colour::Grey,
+            16 => Colour::Tan,
+            17 => Colour::Coral,
+            id => Colour::Unknown(id),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+macro_rules! cosmetic {
+    ($ty:ident { $($id:literal => $variant:ident: $name:literal,)* }) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum $ty {
+            $($variant,)*
+            Unknown(u32),
+        }
+
+        impl $ty {
+            pub fn id(self) -> u32 {
+                match self {
+                    $($ty::$variant => $id,)*
+                    $ty::Unknown(id) => id,
+                }
+            }
+
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $($ty::$variant => Some($name),)*
+                    $ty::Unknown(_) => None,
+                }
+            }
+        }
+
+        impl From<u32> for $ty {
+            fn from(id: u32) -> Self {
+                match id {
+                    $($id => $ty::$variant,)*
+                    id => $ty::Unknown(id),
+                }
+            }
+        }
+    };
+}
+
+cosmetic!(Hat {
+    0 => None: "None",
+    1 => Astronaut: "Astronaut",
+    2 => BaseballCap: "Baseball Cap",
+    3 => BrainSlug: "Brain Slug",
+    4 => BushHat: "Bush Hat",
+    5 => CaptainsHat: "Captain's Hat",
+    6 => DoubleTopHat: "Double Top Hat",
+    7 => Flowerpot: "Flowerpot",
+    8 => Goggles: "Goggles",
+    9 => HardHat: "Hard Hat",
+    10 => Military: "Military",
+    11 => PaperHat: "Paper Hat",
+    12 => PartyHat: "Party Hat",
+    13 => Police: "Police",
+    14 => Stethoscope: "Stethoscope",
+    15 => TopHat: "Top Hat",
+    16 => TowelWizard: "Towel Wizard",
+    17 => Ushanka: "Ushanka",
+    18 => Viking: "Viking",
+    19 => WallGuardCap: "Wall Guard Cap",
+    20 => Snowman: "Snowman",
+    21 => ReindeerAntlers: "Reindeer Antlers",
+    22 => ChristmasLights: "Christmas Lights",
+    23 => SantaHat: "Santa Hat",
+    24 => ChristmasTree: "Christmas Tree",
+    25 => Present: "Present",
+    26 => CandyCanes: "Candy Canes",
+    27 => ElfHat: "Elf Hat",
+    28 => NewYears2018: "2018 Party Hat",
+    29 => WhiteHat: "White Hat",
+    30 => Crown: "Crown",
+    31 => Eyebrows: "Eyebrows",
+    32 => Halo: "Halo",
+    33 => HeroCap: "Hero Cap",
+    34 => PipCap: "Pip Cap",
+    35 => Plunger: "Plunger",
+    36 => ScubaMask: "Scuba Mask",
+    37 => HenryStickmin: "Henry Stickmin",
+    38 => StrawHat: "Straw Hat",
+    39 => TenGallonHat: "Ten Gallon Hat",
+    40 => ThirdEye: "Third Eye",
+    41 => ToiletPaper: "Toilet Paper",
+    42 => ToppatClanLeader: "Toppat Clan Leader",
+    43 => BlackFedora: "Black Fedora",
+    44 => SkiGoggles: "Ski Goggles",
+    45 => Headphones: "Headphones",
+    46 => FiremanHelmet: "Fireman Helmet",
+    47 => PaperMask: "Paper Mask",
+    48 => SecurityHat: "Security Hat",
+    49 => StrapHat: "Strap Hat",
+    50 => Banana: "Banana",
+    51 => Beanie: "Beanie",
+    52 => Bear: "Bear Ears",
+    53 => Cheese: "Cheese",
+    54 => Cherry: "Cherry",
+    55 => Egg: "Egg",
+    56 => GreenFedora: "Green Fedora",
+    57 => Flamingo: "Flamingo",
+    58 => FlowerPin: "Flower Pin",
+    59 => KnightHelmet: "Knight Helmet",
+    60 => Plant: "Plant",
+});
+
+cosmetic!(Pet {
+    0 => None: "None",
+    1 => Alien: "Alien",
+    2 => MiniCrewmate: "Mini Crewmate",
+    3 => Dog: "Dog",
+    4 => HenryStickmin: "Henry Stickmin",
+    5 => Hamster: "Hamster",
+    6 => Robot: "Robot",
+    7 => Ufo: "UFO",
+    8 => Ellie: "Ellie",
+    9 => Squig: "Squig",
+    10 => Bedcrab: "Bedcrab",
+});
+
+cosmetic!(Skin {
+    0 => None: "None",
+    1 => Astronaut: "Astronaut",
+    2 => Captain: "Captain",
+    3 => Mechanic: "Mechanic",
+    4 => Military: "Military",
+    5 => Police: "Police",
+    6 => Scientist: "Scientist",
+    7 => BlackSuit: "Black Suit",
+    8 => WhiteSuit: "White Suit",
+    9 => WallGuard: "Wall Guard",
+    10 => Hazmat: "Hazmat",
+    11 => SecurityGuard: "Security Guard",
+    12 => Tarmac: "Tarmac",
+    13 => Miner: "Miner",
+    14 => Winter: "Winter",
+    15 => Archaeologist: "Archaeologist",
+});
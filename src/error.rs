@@ -1,29 +1,311 @@
 use std::{
+    array::TryFromSliceError,
     error::Error as StdError,
     fmt::{Display, Formatter, Result as FmtResult},
+    io,
+    string::FromUtf16Error,
 };
 
+// Win32 error codes, spelled out so the error types build on any platform
+const ERROR_ACCESS_DENIED: u32 = 5;
+const ERROR_INVALID_HANDLE: u32 = 6;
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
-    EnumModuleError(u32),
-    MissingGaError,
-    ReadError(u32, usize, &'static str),
+    Attach(AttachError),
+    Read(ReadError),
+    Parse(ParseError),
+    Unsupported(&'static str),
+    ProcessGone,
+    // The poll took longer than its deadline; the read may still be running
+    Timeout,
+    Cancelled,
+    Remote(RemoteError),
+    // Installed from a Steam beta branch with no offsets for it; setting
+    // offsets explicitly lifts this
+    UnsupportedBeta(String),
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AttachError {
+    AccessDenied,
+    OpenProcess(u32),
+    PrivilegeQuery(u32),
+    EnumProcesses(u32),
+    EnumModules(u32),
+    GameNotFound,
+    NotAmongUs { executable: String },
+    QueryImage(u32),
+    RegisterWait(u32),
+    MissingGameAssembly,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ReadError {
+    pub code: u32,
+    pub address: usize,
+    pub requested: usize,
+    pub bytes: usize,
+    pub what: &'static str,
+    // Pointer chain being followed, outermost first
+    pub chain: Vec<&'static str>,
+    pub modded: bool,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    InvalidEnumValue { field: &'static str, value: u32 },
+    Implausible { field: &'static str, value: i64 },
+    Slice(TryFromSliceError),
+    Utf16(FromUtf16Error),
+    // The game kept changing underneath the state for this many reads running
+    Unstable { attempts: u32 },
+}
+
+// From talking to a remote agent or a helper in the game
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RemoteError {
+    Connection(io::Error),
+    Protocol(String),
+    // The other end failed; for an agent, the flags carry over from the error
+    // it hit
+    Agent {
+        message: String,
+        transient: bool,
+        fatal: bool,
+    },
+}
+
+impl Error {
+    // Failures which are likely to clear up on the next poll, e.g. a pointer
+    // chain torn by a scene change or a read held up past its deadline
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Read(e) => !is_fatal_code(e.code),
+            Error::Parse(_) | Error::Timeout => true,
+            Error::Remote(RemoteError::Agent { transient, .. }) => *transient,
+            _ => false,
+        }
+    }
+
+    // Failures which won't be fixed without re-attaching to the game
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Error::ProcessGone | Error::Attach(AttachError::AccessDenied) => true,
+            Error::Read(ReadError { code, .. })
+            | Error::Attach(AttachError::OpenProcess(code))
+            | Error::Attach(AttachError::EnumModules(code)) => is_fatal_code(*code),
+            Error::Remote(RemoteError::Agent { fatal, .. }) => *fatal,
+            _ => false,
+        }
+    }
+
+    // Records a step of the pointer chain that led to a failed read
+    #[cfg(windows)]
+    pub(crate) fn within(mut self, step: &'static str) -> Self {
+        if let Error::Read(e) = &mut self {
+            e.chain.insert(0, step);
+        }
+        self
+    }
+}
+
+fn is_fatal_code(code: u32) -> bool {
+    code == ERROR_ACCESS_DENIED || code == ERROR_INVALID_HANDLE
+}
+
+// Resolves a GetLastError code to the system's message text, e.g.
+// "Access is denied. (os error 5)"
+fn os_error(code: u32) -> io::Error {
+    io::Error::from_raw_os_error(code as i32)
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            Error::EnumModuleError(code) => f.write_fmt(format_args!(
+            Error::Attach(e) => e.fmt(f),
+            Error::Read(e) => e.fmt(f),
+            Error::Parse(e) => e.fmt(f),
+            Error::Unsupported(what) => f.write_fmt(format_args!("unsupported: {}", what)),
+            Error::ProcessGone => f.write_str("the game process has exited"),
+            Error::Timeout => f.write_str("reading the game's state timed out"),
+            Error::Cancelled => f.write_str("the watcher was stopped"),
+            Error::Remote(e) => e.fmt(f),
+            Error::UnsupportedBeta(branch) => f.write_fmt(format_args!(
+                "the game is on the {} beta branch, which has no known offsets; \
+                 set them with Game::set_offsets to read it anyway",
+                branch
+            )),
+        }
+    }
+}
+
+impl Display for AttachError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            AttachError::AccessDenied => f.write_str(
+                "access to the game process was denied; see privileges::check() for guidance",
+            ),
+            AttachError::OpenProcess(code) => f.write_fmt(format_args!(
+                "an error occurred opening the game process: {}",
+                os_error(*code)
+            )),
+            AttachError::PrivilegeQuery(code) => f.write_fmt(format_args!(
+                "an error occurred querying this process's privileges: {}",
+                os_error(*code)
+            )),
+            AttachError::EnumProcesses(code) => f.write_fmt(format_args!(
+                "an error occurred enumerating processes: {}",
+                os_error(*code)
+            )),
+            AttachError::GameNotFound => f.write_str("Among Us is not running"),
+            AttachError::EnumModules(code) => f.write_fmt(format_args!(
                 "an error occurred enumerating game's modules: {}",
-                code
+                os_error(*code)
             )),
-            Error::MissingGaError => f.write_str("failed to locate GameAssembly.dll"),
-            Error::ReadError(code, bytes, message) => f.write_fmt(format_args!(
-                "an error occurred reading {}: read {} bytes, error code: {}",
-                message, bytes, code
+            AttachError::MissingGameAssembly => f.write_str("failed to locate GameAssembly.dll"),
+            AttachError::NotAmongUs { executable } => {
+                f.write_fmt(format_args!("the process is {}, not Among Us", executable))
+            }
+            AttachError::QueryImage(code) => f.write_fmt(format_args!(
+                "an error occurred querying the game's executable: {}",
+                os_error(*code)
+            )),
+            AttachError::RegisterWait(code) => f.write_fmt(format_args!(
+                "an error occurred waiting on the game process: {}",
+                os_error(*code)
             )),
         }
     }
 }
 
-impl StdError for Error {}
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_fmt(format_args!("an error occurred reading {}", self.what))?;
+
+        if !self.chain.is_empty() {
+            f.write_fmt(format_args!(" via {}", self.chain.join(" -> ")))?;
+        }
+
+        f.write_fmt(format_args!(
+            " at {:#010x}: read {} of {} bytes: {}",
+            self.address,
+            self.bytes,
+            self.requested,
+            os_error(self.code)
+        ))?;
+
+        if self.modded {
+            f.write_str(" (the client is modded, so offsets may not match)")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            RemoteError::Connection(e) => {
+                f.write_fmt(format_args!("the connection to the agent failed: {}", e))
+            }
+            RemoteError::Protocol(what) => {
+                f.write_fmt(format_args!("unexpected message from the agent: {}", what))
+            }
+            RemoteError::Agent { message, .. } => {
+                f.write_fmt(format_args!("the agent failed: {}", message))
+            }
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            ParseError::InvalidEnumValue { field, value } => {
+                f.write_fmt(format_args!("invalid value for {}: {}", field, value))
+            }
+            ParseError::Implausible { field, value } => {
+                f.write_fmt(format_args!("implausible value for {}: {}", field, value))
+            }
+            ParseError::Slice(_) => f.write_str("failed to convert raw bytes"),
+            ParseError::Utf16(_) => f.write_str("failed to decode UTF-16 string"),
+            ParseError::Unstable { attempts } => f.write_fmt(format_args!(
+                "the game changed while its state was read, {} times running",
+                attempts
+            )),
+        }
+    }
+}
+
+// The wrapped errors' messages are already the Display of these, so the
+// chain skips straight to what caused them
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Attach(e) => e.source(),
+            Error::Read(e) => e.source(),
+            Error::Parse(e) => e.source(),
+            Error::Remote(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl StdError for AttachError {}
+
+impl StdError for ReadError {}
+
+// Connection's message includes the io::Error's
+impl StdError for RemoteError {}
+
+impl StdError for ParseError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ParseError::Slice(e) => Some(e),
+            ParseError::Utf16(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<AttachError> for Error {
+    fn from(e: AttachError) -> Self {
+        Error::Attach(e)
+    }
+}
+
+impl From<ReadError> for Error {
+    fn from(e: ReadError) -> Self {
+        Error::Read(e)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<RemoteError> for Error {
+    fn from(e: RemoteError) -> Self {
+        Error::Remote(e)
+    }
+}
+
+impl From<TryFromSliceError> for Error {
+    fn from(e: TryFromSliceError) -> Self {
+        Error::Parse(ParseError::Slice(e))
+    }
+}
+
+impl From<FromUtf16Error> for Error {
+    fn from(e: FromUtf16Error) -> Self {
+        Error::Parse(ParseError::Utf16(e))
+    }
+}
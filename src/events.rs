@@ -0,0 +1,148 @@
+use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    state::State,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    NameChanged { id: u8, old: String, new: String },
+    ColourChanged { id: u8, old: Colour, new: Colour },
+    HatChanged { id: u8, old: Hat, new: Hat },
+    PetChanged { id: u8, old: Pet, new: Pet },
+    SkinChanged { id: u8, old: Skin, new: Skin },
+}
+
+// Which fields diff_with compares: the reader leaves out what its
+// StateReadOptions skip, and those shouldn't look like changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compared {
+    pub cosmetics: bool,
+}
+
+impl Default for Compared {
+    fn default() -> Self {
+        Compared { cosmetics: true }
+    }
+}
+
+// Names that are empty on either side weren't read, whether skipped by
+// StateReadOptions or tolerated as a warning, so they aren't compared
+pub fn diff(old: &State, new: &State) -> Vec<Event> {
+    diff_with(old, new, Compared::default())
+}
+
+pub fn diff_with(old: &State, new: &State, compared: Compared) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    for player in new.players() {
+        let previous = match old.players().iter().find(|p| p.id == player.id) {
+            Some(previous) => previous,
+            None => continue,
+        };
+
+        let id = player.id;
+
+        if previous.name != player.name && !previous.name.is_empty() && !player.name.is_empty() {
+            events.push(Event::NameChanged {
+                id,
+                old: previous.name.clone(),
+                new: player.name.clone(),
+            });
+        }
+        if previous.colour != player.colour {
+            events.push(Event::ColourChanged {
+                id,
+                old: previous.colour,
+                new: player.colour,
+            });
+        }
+        if previous.hat != player.hat && compared.cosmetics {
+            events.push(Event::HatChanged {
+                id,
+                old: previous.hat,
+                new: player.hat,
+            });
+        }
+        if previous.pet != player.pet && compared.cosmetics {
+            events.push(Event::PetChanged {
+                id,
+                old: previous.pet,
+                new: player.pet,
+            });
+        }
+        if previous.skin != player.skin && compared.cosmetics {
+            events.push(Event::SkinChanged {
+                id,
+                old: previous.skin,
+                new: player.skin,
+            });
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Player;
+
+    fn lobby(players: Vec<Player>) -> State {
+        State::Lobby { players }
+    }
+
+    #[test]
+    fn reports_changed_fields() {
+        let old = lobby(vec![Player::test(0, "Red"), Player::test(1, "Blue")]);
+        let mut new = old.clone();
+        if let State::Lobby { players } = &mut new {
+            players[0].name = "Rouge".to_string();
+            players[1].hat = Hat::from(15);
+        }
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Event::NameChanged {
+                    id: 0,
+                    old: "Red".to_string(),
+                    new: "Rouge".to_string(),
+                },
+                Event::HatChanged {
+                    id: 1,
+                    old: Hat::None,
+                    new: Hat::TopHat,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unread_names_and_new_players() {
+        let old = lobby(vec![Player::test(0, "")]);
+        let new = lobby(vec![Player::test(0, "Red"), Player::test(1, "Blue")]);
+
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn skips_cosmetics_that_were_not_read() {
+        let old = lobby(vec![Player::test(0, "Red")]);
+        let mut new = old.clone();
+        if let State::Lobby { players } = &mut new {
+            players[0].hat = Hat::from(15);
+            players[0].colour = Colour::from(1);
+        }
+
+        assert_eq!(
+            diff_with(&old, &new, Compared { cosmetics: false }),
+            vec![Event::ColourChanged {
+                id: 0,
+                old: Colour::Red,
+                new: Colour::Blue,
+            }]
+        );
+    }
+}
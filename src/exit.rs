@@ -0,0 +1,120 @@
+use std::{
+    ffi::c_void,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use winapi::{
+    shared::ntdef::{BOOLEAN, HANDLE},
+    um::{
+        errhandlingapi::GetLastError,
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        processthreadsapi::OpenProcess,
+        threadpoollegacyapiset::UnregisterWaitEx,
+        winbase::{RegisterWaitForSingleObject, INFINITE},
+        winnt::{SYNCHRONIZE, WT_EXECUTEONLYONCE},
+    },
+};
+
+use crate::{error::AttachError, Result};
+
+// Resolves once the game process exits. Owns its own handle so it can outlive
+// the Game it was created from.
+pub struct ProcessExit {
+    shared: Arc<Shared>,
+    process: usize,
+    wait: usize,
+}
+
+#[derive(Default)]
+struct Shared {
+    exited: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl ProcessExit {
+    pub(crate) fn new(pid: usize) -> Result<Self> {
+        let process = unsafe { OpenProcess(SYNCHRONIZE, false.into(), pid as u32) };
+
+        if process.is_null() {
+            return Err(AttachError::OpenProcess(unsafe { GetLastError() }).into());
+        }
+
+        let shared = Arc::new(Shared::default());
+        let context = Arc::into_raw(shared.clone()) as *mut c_void;
+        let mut wait: HANDLE = std::ptr::null_mut();
+
+        let registered = unsafe {
+            RegisterWaitForSingleObject(
+                &mut wait,
+                process,
+                Some(on_exit),
+                context,
+                INFINITE,
+                WT_EXECUTEONLYONCE,
+            )
+        };
+
+        if registered == 0 {
+            let code = unsafe { GetLastError() };
+            unsafe {
+                drop(Arc::from_raw(context as *const Shared));
+                CloseHandle(process);
+            }
+            return Err(AttachError::RegisterWait(code).into());
+        }
+
+        Ok(ProcessExit {
+            shared,
+            process: process as usize,
+            wait: wait as usize,
+        })
+    }
+}
+
+unsafe extern "system" fn on_exit(context: *mut c_void, _timed_out: BOOLEAN) {
+    let shared = &*(context as *const Shared);
+
+    shared.exited.store(true, Ordering::SeqCst);
+
+    if let Some(waker) = shared.waker.lock().ok().and_then(|mut waker| waker.take()) {
+        waker.wake();
+    }
+}
+
+impl Future for ProcessExit {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.exited.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+
+        if let Ok(mut waker) = self.shared.waker.lock() {
+            *waker = Some(cx.waker().clone());
+        }
+
+        // The process may have exited while the waker was being stored
+        if self.shared.exited.load(Ordering::SeqCst) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for ProcessExit {
+    fn drop(&mut self) {
+        unsafe {
+            // Blocks until any running callback has finished with the context
+            UnregisterWaitEx(self.wait as HANDLE, INVALID_HANDLE_VALUE);
+            drop(Arc::from_raw(Arc::as_ptr(&self.shared)));
+            CloseHandle(self.process as HANDLE);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// A stand-in for the game, for building and demoing overlays and bots without
+// launching Among Us. Each state() moves it on a step: either through a fixed
+// script, or through a made-up evening where lobbies fill, games start,
+// players are killed and ejected, and meetings run their course.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    state::{MeetingState, Player, State, StateSource},
+    Result,
+};
+
+const NAMES: [&str; 10] = [
+    "Alice", "Bob", "Carol", "Dave", "Erin", "Frank", "Grace", "Heidi", "Ivan", "Judy",
+];
+const TASKS_PER_PLAYER: u32 = 5;
+
+pub struct FakeGame {
+    source: Mutex<Source>,
+}
+
+enum Source {
+    Script { states: Vec<State>, next: usize },
+    Random(Evening),
+}
+
+impl FakeGame {
+    // The same seed plays out the same way
+    pub fn random(seed: u64) -> Self {
+        FakeGame {
+            source: Mutex::new(Source::Random(Evening::new(seed))),
+        }
+    }
+
+    // Plays the states in order, then starts again from the first
+    pub fn scripted(states: Vec<State>) -> Self {
+        FakeGame {
+            source: Mutex::new(Source::Script { states, next: 0 }),
+        }
+    }
+
+    pub fn state(&self) -> Result<State> {
+        Ok(match &mut *self.lock() {
+            Source::Script { states, next } if !states.is_empty() => {
+                let state = states[*next % states.len()].clone();
+                *next += 1;
+                state
+            }
+            Source::Script { .. } => State::Menu,
+            Source::Random(evening) => evening.step(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Source> {
+        self.source
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct Evening {
+    rng: u64,
+    state: State,
+    lobby_size: usize,
+    // Steps left in the current meeting stage
+    stage_steps: u32,
+}
+
+impl Evening {
+    fn new(seed: u64) -> Self {
+        Evening {
+            // xorshift gets stuck on zero
+            rng: seed | 1,
+            state: State::Menu,
+            lobby_size: 0,
+            stage_steps: 0,
+        }
+    }
+
+    // Fine for made-up games; xorshift64
+    fn next(&mut self, below: u64) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng % below.max(1)
+    }
+
+    fn chance(&mut self, one_in: u64) -> bool {
+        self.next(one_in) == 0
+    }
+
+    // The current state, then moves on from it
+    fn step(&mut self) -> State {
+        let state = self.state.clone();
+
+        self.state = match state.clone() {
+            State::Menu => {
+                self.lobby_size = 4 + self.next(7) as usize;
+                State::Lobby {
+                    players: Vec::new(),
+                }
+            }
+            State::Lobby { mut players } if players.len() < self.lobby_size => {
+                let id = players.len() as u8;
+                players.push(self.player(id));
+                State::Lobby { players }
+            }
+            State::Lobby { players } => self.start(players),
+            State::InGame {
+                meeting,
+                players,
+                tasks_completed,
+                tasks_total,
+            } => self.play(meeting, players, tasks_completed, tasks_total),
+            State::Ended { mut players } => {
+                for player in &mut players {
+                    player.dead = false;
+                    player.impostor = false;
+                }
+                State::Lobby { players }
+            }
+        };
+
+        state
+    }
+
+    fn player(&mut self, id: u8) -> Player {
+        Player {
+            id,
+            name: NAMES[id as usize % NAMES.len()].to_string(),
+            colour: Colour::from(i32::from(id)),
+            hat: Hat::from(self.next(20) as u32),
+            pet: Pet::from(self.next(5) as u32),
+            skin: Skin::from(self.next(5) as u32),
+            disconnected: false,
+            #[cfg(windows)]
+            tasks_addr: 0,
+            impostor: false,
+            dead: false,
+            #[cfg(windows)]
+            game_object_addr: 0,
+            extra_roles: HashMap::new(),
+        }
+    }
+
+    fn start(&mut self, mut players: Vec<Player>) -> State {
+        let impostors = if players.len() >= 7 { 2 } else { 1 };
+        while players.iter().filter(|p| p.impostor).count() < impostors {
+            let chosen = self.next(players.len() as u64) as usize;
+            players[chosen].impostor = true;
+        }
+
+        let crew = (players.len() - impostors) as u32;
+        State::InGame {
+            meeting: MeetingState::Proceeding,
+            players,
+            tasks_completed: 0,
+            tasks_total: crew * TASKS_PER_PLAYER,
+        }
+    }
+
+    fn play(
+        &mut self,
+        meeting: MeetingState,
+        mut players: Vec<Player>,
+        mut tasks_completed: u32,
+        tasks_total: u32,
+    ) -> State {
+        let meeting = match meeting {
+            MeetingState::Proceeding => {
+                tasks_completed = (tasks_completed + self.next(3) as u32).min(tasks_total);
+
+                if self.chance(8) {
+                    self.kill(&mut players, false);
+                }
+
+                if self.chance(12) {
+                    self.stage_steps = 1;
+                    MeetingState::Animating
+                } else {
+                    MeetingState::Proceeding
+                }
+            }
+            stage if self.stage_steps > 1 => {
+                self.stage_steps -= 1;
+                stage
+            }
+            stage => {
+                let (next, steps) = match stage {
+                    MeetingState::Animating => (MeetingState::Discussion, 3),
+                    MeetingState::Discussion => (MeetingState::NotVoted, 3),
+                    MeetingState::NotVoted => (MeetingState::Voted, 1),
+                    MeetingState::Voted => (MeetingState::Results, 1),
+                    _ => {
+                        // Somebody is usually voted out
+                        if !self.chance(3) {
+                            self.kill(&mut players, true);
+                        }
+                        (MeetingState::Proceeding, 0)
+                    }
+                };
+                self.stage_steps = steps;
+                next
+            }
+        };
+
+        let alive = |impostor: bool| {
+            players
+                .iter()
+                .filter(|p| !p.dead && p.impostor == impostor)
+                .count()
+        };
+        let (impostors, crew) = (alive(true), alive(false));
+
+        if impostors == 0 || impostors >= crew || tasks_completed == tasks_total {
+            return State::Ended { players };
+        }
+
+        State::InGame {
+            meeting,
+            players,
+            tasks_completed,
+            tasks_total,
+        }
+    }
+
+    // Anyone alive can be ejected; only crewmates are killed
+    fn kill(&mut self, players: &mut [Player], ejection: bool) {
+        let candidates: Vec<usize> = (0..players.len())
+            .filter(|&i| !players[i].dead && (ejection || !players[i].impostor))
+            .collect();
+
+        if !candidates.is_empty() {
+            let chosen = candidates[self.next(candidates.len() as u64) as usize];
+            players[chosen].dead = true;
+        }
+    }
+}
+
+impl StateSource for FakeGame {
+    fn state(&self) -> Result<State> {
+        FakeGame::state(self)
+    }
+}
@@ -0,0 +1,124 @@
+// Wraps a state source and makes chosen reads fail, so an app's retry and
+// reattach handling can be tested without waiting for the real game to break.
+// Faults are tied to the read count, so a test sees the same failures every run.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    error::{Error, ParseError, ReadError},
+    state::{State, StateSource},
+    Result,
+};
+
+// ReadProcessMemory's usual complaint about a pointer into freed memory
+const ERROR_PARTIAL_COPY: u32 = 299;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    // A read that fails partway down a pointer chain
+    Transient,
+    // A value caught mid-write, failing the plausibility checks
+    Torn,
+    ProcessGone,
+}
+
+impl Fault {
+    fn error(self) -> Error {
+        match self {
+            Fault::Transient => Error::Read(ReadError {
+                code: ERROR_PARTIAL_COPY,
+                address: 0,
+                requested: 0,
+                bytes: 0,
+                what: "injected fault",
+                chain: Vec::new(),
+                modded: false,
+            }),
+            Fault::Torn => Error::Parse(ParseError::Implausible {
+                field: "injected fault",
+                value: -1,
+            }),
+            Fault::ProcessGone => Error::ProcessGone,
+        }
+    }
+}
+
+pub struct FaultyGame<S> {
+    inner: S,
+    // Reads are counted from zero
+    at: Vec<(u64, Fault)>,
+    every: Vec<(u64, Fault)>,
+    // From this read on, every read fails with ProcessGone
+    gone_from: Option<u64>,
+    reads: AtomicU64,
+}
+
+impl<S: StateSource> FaultyGame<S> {
+    pub fn new(inner: S) -> Self {
+        FaultyGame {
+            inner,
+            at: Vec::new(),
+            every: Vec::new(),
+            gone_from: None,
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    pub fn fail_at(mut self, read: u64, fault: Fault) -> Self {
+        self.at.push((read, fault));
+        self
+    }
+
+    // Fails reads interval - 1, 2 * interval - 1 and so on
+    pub fn fail_every(mut self, interval: u64, fault: Fault) -> Self {
+        self.every.push((interval.max(1), fault));
+        self
+    }
+
+    // Like the process exiting for good: the read and every one after it fail
+    pub fn exit_at(mut self, read: u64) -> Self {
+        self.gone_from = Some(read);
+        self
+    }
+
+    pub fn reads(&self) -> u64 {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn fault(&self, read: u64) -> Option<Fault> {
+        if self.gone_from.is_some_and(|gone_from| read >= gone_from) {
+            return Some(Fault::ProcessGone);
+        }
+
+        self.at
+            .iter()
+            .find(|&&(at, _)| at == read)
+            .or_else(|| {
+                self.every
+                    .iter()
+                    .find(|&&(interval, _)| (read + 1).is_multiple_of(interval))
+            })
+            .map(|&(_, fault)| fault)
+    }
+}
+
+impl<S: StateSource> StateSource for FaultyGame<S> {
+    fn state(&self) -> Result<State> {
+        let read = self.reads.fetch_add(1, Ordering::Relaxed);
+
+        match self.fault(read) {
+            Some(fault) => {
+                tracing::debug!("injecting {:?} into read {}", fault, read);
+                Err(fault.error())
+            }
+            None => self.inner.state(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// C API for consumers that can't link Rust directly. Every function is safe to
+// call with null pointers; failures return null and leave a message for
+// tc_last_error on the calling thread.
+
+use std::{
+    cell::RefCell,
+    ffi::CString,
+    os::raw::c_char,
+    ptr::{null, null_mut},
+};
+
+use crate::game::Game;
+
+pub struct TcGame {
+    game: Game,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Attaches to the first running game; free it with `tc_detach`.
+#[no_mangle]
+pub extern "C" fn tc_attach() -> *mut TcGame {
+    match Game::find() {
+        Ok(game) => Box::into_raw(Box::new(TcGame { game })),
+        Err(e) => {
+            set_last_error(e.to_string());
+            null_mut()
+        }
+    }
+}
+
+/// Attaches to the game with the given process id; free it with `tc_detach`.
+#[no_mangle]
+pub extern "C" fn tc_attach_pid(pid: u32) -> *mut TcGame {
+    match Game::from_pid(pid as usize) {
+        Ok(game) => Box::into_raw(Box::new(TcGame { game })),
+        Err(e) => {
+            set_last_error(e.to_string());
+            null_mut()
+        }
+    }
+}
+
+/// Closes the game's process handle and frees it.
+///
+/// # Safety
+///
+/// `game` must be null or a pointer returned by `tc_attach` or
+/// `tc_attach_pid` that hasn't been detached.
+#[no_mangle]
+pub unsafe extern "C" fn tc_detach(game: *mut TcGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Reads the current state as a JSON document; free it with `tc_free`.
+///
+/// # Safety
+///
+/// `game` must be null or a live pointer from `tc_attach`/`tc_attach_pid`,
+/// not used from another thread at the same time.
+#[no_mangle]
+pub unsafe extern "C" fn tc_poll_state_json(game: *mut TcGame) -> *mut c_char {
+    let game = match game.as_ref() {
+        Some(game) => &game.game,
+        None => {
+            set_last_error("game is null".to_string());
+            return null_mut();
+        }
+    };
+
+    let json = game
+        .state()
+        .map_err(|e| e.to_string())
+        .and_then(|state| serde_json::to_string(&state).map_err(|e| e.to_string()))
+        .and_then(|json| CString::new(json).map_err(|e| e.to_string()));
+
+    match json {
+        Ok(json) => json.into_raw(),
+        Err(message) => {
+            set_last_error(message);
+            null_mut()
+        }
+    }
+}
+
+/// Frees a string returned by this library.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library that hasn't
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn tc_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Describes the last failure on this thread. Valid until the next failing
+/// call on the same thread; null if nothing has failed yet.
+#[no_mangle]
+pub extern "C" fn tc_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(null(), |message| message.as_ptr())
+    })
+}
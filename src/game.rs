@@ -1,64 +1,239 @@
 use std::{
-    convert::TryInto,
+    borrow::Cow,
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
     ffi::c_void,
-    mem::{size_of, MaybeUninit},
+    fmt::{Display, Formatter, Result as FmtResult},
+    mem::{size_of, swap, take, MaybeUninit},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, MutexGuard,
+    },
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
 };
 
+use crossbeam_channel::Sender;
 use winapi::{
-    shared::minwindef::HMODULE,
+    shared::{minwindef::HMODULE, ntdef::HANDLE, winerror::ERROR_ACCESS_DENIED},
     um::{
-        errhandlingapi::GetLastError,
+        errhandlingapi::{GetLastError, SetLastError},
+        handleapi::CloseHandle,
         memoryapi::ReadProcessMemory,
-        processthreadsapi::OpenProcess,
+        minwinbase::STILL_ACTIVE,
+        processthreadsapi::{GetExitCodeProcess, OpenProcess},
         psapi::{EnumProcessModulesEx, GetModuleBaseNameW},
-        winnt::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+        winnt::{PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ},
     },
 };
 
-use crate::{error::Error, Result};
+use crate::{
+    builder::GameBuilder,
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    error::{AttachError, Error, ParseError, ReadError},
+    exit::ProcessExit,
+    gather::ReadPlan,
+    offsets::OffsetTable,
+    packs::{FieldBase, FieldKind, OffsetPack, RoleValue},
+    process,
+    retry::RetryPolicy,
+    state::{GameUSize, Phase, StateSource},
+    stats::{Counters, ReadHook, ReadRecord, ReadStats},
+    window::Window,
+    Result,
+};
+
+// Moved to state; still reachable from here for existing imports
+pub use crate::state::{MeetingState, Player, State};
+
+const MAX_PLAYERS: GameUSize = 15;
+const MAX_STRING_LEN: GameUSize = 64;
+const NAME_CACHE_SIZE: usize = 64;
+
+const GAME_ASSEMBLY: &str = "GameAssembly.dll";
+
+// PlayerInfo fields start after the klass/monitor header
+const PLAYER_FIELDS_OFFSET: GameUSize = 0x8;
+const PLAYER_STRUCT_SIZE: usize = 0x2C;
+const PLAYER_STRUCT_GAP: usize = 0x100;
+// List<T>'s items pointer is followed by its size
+const PLAYER_LIST_ITEMS_OFFSET: GameUSize = 0x8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    // PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, modules found through psapi
+    Full,
+    // PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ, modules found
+    // through a toolhelp snapshot
+    Limited,
+}
 
-type GameUSize = u32;
+// How the module the offsets are relative to is recognised by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleMatch {
+    Exact(String),
+    // The default, as Windows itself doesn't care about case
+    IgnoreCase(String),
+    // Case-insensitive, for repacks that rename the DLL with a prefix
+    Suffix(String),
+}
+
+impl ModuleMatch {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            ModuleMatch::Exact(expected) => name == expected,
+            ModuleMatch::IgnoreCase(expected) => name.eq_ignore_ascii_case(expected),
+            ModuleMatch::Suffix(suffix) => name
+                .len()
+                .checked_sub(suffix.len())
+                .and_then(|start| name.get(start..))
+                .is_some_and(|end| end.eq_ignore_ascii_case(suffix)),
+        }
+    }
+}
+
+impl Default for ModuleMatch {
+    fn default() -> Self {
+        ModuleMatch::IgnoreCase(GAME_ASSEMBLY.to_string())
+    }
+}
+
+// Which storefront the running copy of the game came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Steam,
+    Epic,
+    Itch,
+    // Including Game Pass
+    MicrosoftStore,
+    Other,
+}
+
+impl Display for Distribution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Distribution::Steam => "Steam",
+            Distribution::Epic => "Epic Games Store",
+            Distribution::Itch => "itch.io",
+            Distribution::MicrosoftStore => "Microsoft Store",
+            Distribution::Other => "unknown",
+        })
+    }
+}
 
 pub struct Game {
-    handle: usize,
+    pid: usize,
+    access: AccessMode,
+    handle: ProcessHandle,
+    image_path: PathBuf,
+    distribution: Distribution,
+    mod_indicators: Vec<PathBuf>,
+    module: ModuleMatch,
     ga_addr: GameUSize,
+    retry_policy: RetryPolicy,
+    offsets: OffsetTable,
+    // Steam beta branch, if installed from one
+    branch: Option<String>,
+    // False on a beta branch without offsets of its own, until some are set
+    offsets_known: bool,
+    cache_settings: CacheSettings,
+    string_decoding: StringDecoding,
+    consistency: Consistency,
+    // Threads the per-player reads are spread over; 1 reads them in turn
+    player_threads: usize,
+    // Started by set_player_threads, so polls don't pay for spawning them
+    player_pool: Option<PlayerPool>,
+    read_options: StateReadOptions,
+    offset_packs: Vec<OffsetPack>,
+    // Statics addresses of the singleton classes, indexed by
+    // InstancedClass::CACHE_SLOT; 0 until resolved
+    statics_cache: [AtomicU32; 3],
+    last_internal_state: AtomicU32,
+    // Decoded names and their length in UTF-16 units, keyed by the address
+    // of their string object
+    name_cache: Mutex<HashMap<GameUSize, (GameUSize, String)>>,
+    counters: Counters,
+    read_hook: Option<ReadHook>,
 }
 
-#[derive(Debug, Clone)]
-pub enum State {
-    Menu,
-    Lobby {
-        // code: String,
-        players: Vec<Player>,
-    },
-    InGame {
-        // code: String,
-        meeting: MeetingState,
-        players: Vec<Player>,
-        tasks_completed: GameUSize,
-        tasks_total: GameUSize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringDecoding {
+    // Invalid UTF-16 fails the read
+    Strict,
+    // Invalid UTF-16 is replaced with U+FFFD
+    Lossy,
+}
+
+// Whether a state is checked for the game having moved on while it was read,
+// e.g. a player joining halfway through the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Consistency {
+    // Take the state as read
+    #[default]
+    Unchecked,
+    // Re-read the client state, player list and task total after the state and
+    // read it again if any of them changed; after the retries run out the poll
+    // fails with ParseError::Unstable
+    Verified {
+        retries: u32,
     },
 }
 
-#[repr(u32)]
+// Parts of the state to read; anything skipped comes back empty or defaulted
 #[derive(Debug, Clone)]
-pub enum MeetingState {
-    Animating,
-    Discussion,
-    NotVoted,
-    Voted,
-    Results,
-    Proceeding,
+pub struct StateReadOptions {
+    pub players: bool,
+    // Hats, pets and skins; defaulted to None when skipped
+    pub cosmetics: bool,
+    pub names: bool,
+    pub tasks: bool,
+    pub meeting: bool,
+}
+
+impl Default for StateReadOptions {
+    fn default() -> Self {
+        StateReadOptions {
+            players: true,
+            cosmetics: true,
+            names: true,
+            tasks: true,
+            meeting: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Player {
-    id: u8,
-    pub name: String,
-    pub colour: i32,
-    hat: u32,
-    pet: u32,
-    skin: u32,
+pub struct CacheSettings {
+    // Keep singleton statics addresses between polls
+    pub statics: bool,
+    // Keep decoded names keyed by their string's address
+    pub names: bool,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            statics: true,
+            names: true,
+        }
+    }
+}
+
+// The fields that come with the player struct itself; the name and extra roles
+// take further reads, so they're only fetched when asked for
+#[derive(Debug, Clone)]
+pub struct PlayerRef {
+    addr: GameUSize,
+    name_addr: GameUSize,
+    pub id: u8,
+    pub colour: Colour,
+    pub hat: Hat,
+    pub pet: Pet,
+    pub skin: Skin,
     pub disconnected: bool,
     tasks_addr: GameUSize,
     pub impostor: bool,
@@ -66,8 +241,104 @@ pub struct Player {
     game_object_addr: GameUSize,
 }
 
-#[repr(u32)]
-#[allow(dead_code)]
+impl PlayerRef {
+    pub fn key(&self) -> u8 {
+        self.id
+    }
+
+    pub fn name(&self, game: &Game) -> Result<String> {
+        let mut name = String::new();
+        unsafe { game.read_name_into(self.name_addr, &mut Vec::new(), &mut name) }?;
+
+        Ok(name)
+    }
+
+    pub fn extra_roles(&self, game: &Game) -> Result<HashMap<String, RoleValue>> {
+        unsafe { game.read_extra_roles(self, &mut Warnings(None)) }
+    }
+
+    pub fn read(&self, game: &Game) -> Result<Player> {
+        unsafe { game.read_player(self.clone(), &mut Warnings(None)) }
+    }
+}
+
+// Storage reused across polls by Game::state_into, so that polling doesn't
+// allocate once the buffers have grown to fit
+#[derive(Debug, Clone)]
+pub struct StateBuffers {
+    state: State,
+    // Keeps the players' allocations while the state is Menu
+    spare_players: Vec<Player>,
+    player_addrs: Vec<GameUSize>,
+    utf16: Vec<u16>,
+}
+
+impl StateBuffers {
+    pub fn new() -> Self {
+        StateBuffers {
+            state: State::Menu,
+            spare_players: Vec::new(),
+            player_addrs: Vec::new(),
+            utf16: Vec::new(),
+        }
+    }
+
+    // Holds the most recent state, or Menu after a failed read
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+}
+
+impl Default for StateBuffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default, PartialEq, Eq)]
+struct StateShape {
+    internal_state: u32,
+    player_list_addr: GameUSize,
+    player_count: GameUSize,
+    tasks_total: GameUSize,
+}
+
+struct GameDataHeader {
+    player_list_addr: GameUSize,
+    tasks_total: GameUSize,
+    tasks_completed: GameUSize,
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub field: &'static str,
+    pub error: Error,
+}
+
+// Collects failed reads of non-essential fields when a partial state is
+// acceptable, otherwise passes the error straight through
+struct Warnings(Option<Vec<Warning>>);
+
+impl Warnings {
+    fn tolerate<T>(
+        &mut self,
+        field: &'static str,
+        result: Result<T>,
+        fallback: impl FnOnce() -> T,
+    ) -> Result<T> {
+        match (result, &mut self.0) {
+            (Ok(value), _) => Ok(value),
+            (Err(error), Some(warnings)) => {
+                tracing::debug!("failed to read {}: {}", field, error);
+                warnings.push(Warning { field, error });
+                Ok(fallback())
+            }
+            (Err(error), None) => Err(error),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 enum InternalState {
     NotJoined,
     Joined,
@@ -75,92 +346,581 @@ enum InternalState {
     Ended,
 }
 
+impl TryFrom<u32> for InternalState {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(InternalState::NotJoined),
+            1 => Ok(InternalState::Joined),
+            2 => Ok(InternalState::Started),
+            3 => Ok(InternalState::Ended),
+            value => Err(ParseError::InvalidEnumValue {
+                field: "internal state",
+                value,
+            }
+            .into()),
+        }
+    }
+}
+
+impl TryFrom<u32> for MeetingState {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(MeetingState::Animating),
+            1 => Ok(MeetingState::Discussion),
+            2 => Ok(MeetingState::NotVoted),
+            3 => Ok(MeetingState::Voted),
+            4 => Ok(MeetingState::Results),
+            5 => Ok(MeetingState::Proceeding),
+            value => Err(ParseError::InvalidEnumValue {
+                field: "meeting state",
+                value,
+            }
+            .into()),
+        }
+    }
+}
+
 impl Game {
+    pub fn builder() -> GameBuilder {
+        GameBuilder::new()
+    }
+
+    // Falls back to limited access when full access doesn't get as far as
+    // finding GameAssembly.dll, as happens with Store builds
     pub fn from_pid(pid: usize) -> Result<Self> {
-        const MAX_MODULE_COUNT: usize = 128;
-        const MAX_MODULE_NAME_LEN: usize = 64;
-
-        let handle = unsafe {
-            OpenProcess(
-                PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
-                false.into(),
-                pid as u32,
-            )
-        };
+        with_access_fallback(|access| Self::from_pid_with_access(pid, access))
+    }
 
-        let mut modules: Vec<HMODULE> = Vec::with_capacity(MAX_MODULE_COUNT);
-        let mut count_bytes = 0;
+    pub fn from_pid_with_access(pid: usize, access: AccessMode) -> Result<Self> {
+        Self::from_pid_with_module(pid, access, ModuleMatch::default())
+    }
 
-        let enum_modules_result = unsafe {
-            EnumProcessModulesEx(
-                handle,
-                modules.as_mut_ptr(),
-                size_of::<isize>() as u32 * MAX_MODULE_COUNT as u32,
-                &mut count_bytes,
-                0x03, // Get both 32- and 64-bit modules
-            )
+    // For copies where GameAssembly.dll goes by another name; kept for
+    // reattaching
+    pub fn from_pid_with_module(
+        pid: usize,
+        access: AccessMode,
+        module: ModuleMatch,
+    ) -> Result<Self> {
+        let rights = match access {
+            AccessMode::Full => PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+            AccessMode::Limited => PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ,
         };
 
-        unsafe { modules.set_len(count_bytes as usize / size_of::<isize>()) };
+        let handle = unsafe { OpenProcess(rights, false.into(), pid as u32) };
 
-        if enum_modules_result == 0 {
-            return Err(Error::EnumModuleError(unsafe { GetLastError() }).into());
+        if handle.is_null() {
+            return Err(match unsafe { GetLastError() } {
+                ERROR_ACCESS_DENIED => AttachError::AccessDenied,
+                code => AttachError::OpenProcess(code),
+            }
+            .into());
         }
 
-        let ga_addr = modules.into_iter().find_map(|hm| {
-            let mut mod_name: Vec<u16> = Vec::with_capacity(MAX_MODULE_NAME_LEN);
+        let handle = ProcessHandle(handle);
 
-            let len = unsafe {
-                GetModuleBaseNameW(
-                    handle,
-                    hm,
-                    mod_name.as_mut_ptr(),
-                    MAX_MODULE_NAME_LEN as u32,
-                )
-            } as usize;
+        // Dropping the handle on error closes it
+        let (image_path, ga_addr) = unsafe { inspect(handle.0, pid, access, &module) }?;
+
+        let branch = process::steam_branch(&image_path);
+        let beta_offsets = branch.as_deref().and_then(OffsetTable::beta);
+        if let (Some(branch), None) = (&branch, &beta_offsets) {
+            tracing::warn!("no offsets for the {} beta branch", branch);
+        }
+
+        Ok(Game {
+            pid,
+            access,
+            handle,
+            mod_indicators: process::find_mod_indicators(pid, &image_path),
+            distribution: process::distribution(pid, &image_path),
+            image_path,
+            module,
+            ga_addr,
+            retry_policy: RetryPolicy::default(),
+            offsets_known: branch.is_none() || beta_offsets.is_some(),
+            offsets: beta_offsets.unwrap_or_default(),
+            branch,
+            cache_settings: CacheSettings::default(),
+            string_decoding: StringDecoding::Strict,
+            consistency: Consistency::default(),
+            player_threads: 1,
+            player_pool: None,
+            read_options: StateReadOptions::default(),
+            offset_packs: Vec::new(),
+            statics_cache: Default::default(),
+            last_internal_state: AtomicU32::new(u32::MAX),
+            name_cache: Mutex::new(HashMap::new()),
+            counters: Counters::default(),
+            read_hook: None,
+        })
+    }
 
-            unsafe { mod_name.set_len(len) };
+    // Attaches to the first running game that has finished loading
+    pub fn find() -> Result<Self> {
+        with_access_fallback(Self::find_with_access)
+    }
+
+    pub fn find_with_access(access: AccessMode) -> Result<Self> {
+        Self::find_with_module(access, &ModuleMatch::default())
+    }
+
+    pub fn find_with_module(access: AccessMode, module: &ModuleMatch) -> Result<Self> {
+        let mut last_error = None;
 
-            if String::from_utf16(&mod_name).ok()? != "GameAssembly.dll" {
-                return None;
+        for pid in process::find_game_pids()? {
+            match Self::from_pid_with_module(pid, access, module.clone()) {
+                Ok(game) => return Ok(game),
+                Err(e) => last_error = Some(e),
             }
+        }
 
-            Some(hm)
-        });
+        Err(last_error.unwrap_or_else(|| AttachError::GameNotFound.into()))
+    }
 
-        if let Some(ga_addr) = ga_addr.map(|addr| addr as u32) {
-            Ok(Game {
-                handle: handle as usize,
-                ga_addr,
-            })
-        } else {
-            Err(Error::MissingGaError.into())
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn image_path(&self) -> &Path {
+        &self.image_path
+    }
+
+    // Mods shift struct layouts, so anything read from a modded client may be
+    // wrong
+    pub fn is_modded(&self) -> bool {
+        !self.mod_indicators.is_empty()
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        self.distribution
+    }
+
+    pub fn module(&self) -> &ModuleMatch {
+        &self.module
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+
+    pub fn is_beta(&self) -> bool {
+        self.branch.is_some()
+    }
+
+    // Loaded modules which gave the mod loader away
+    pub fn mod_indicators(&self) -> &[PathBuf] {
+        &self.mod_indicators
+    }
+
+    pub fn is_running(&self) -> bool {
+        let mut exit_code = 0;
+
+        let queried = unsafe { GetExitCodeProcess(self.handle.0, &mut exit_code) };
+
+        queried != 0 && exit_code == STILL_ACTIVE
+    }
+
+    pub fn window(&self) -> Option<Window> {
+        Window::find(self.pid)
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.window().is_some_and(|window| window.is_foreground())
+    }
+
+    // Resolves when the attached process exits, without polling
+    pub fn exited(&self) -> Result<ProcessExit> {
+        ProcessExit::new(self.pid)
+    }
+
+    // Once the attached process has exited, waits for a new one and attaches to
+    // it, keeping this game's settings
+    pub fn reattach(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.reattach_unless(timeout, || false)
+    }
+
+    pub(crate) fn reattach_unless(
+        &mut self,
+        timeout: Option<Duration>,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+        if self.is_running() {
+            return Ok(());
+        }
+
+        let started = Instant::now();
+
+        loop {
+            if cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            match Self::find_with_module(self.access, &self.module) {
+                Ok(mut game) => {
+                    tracing::info!("reattached to pid {}", game.pid);
+                    swap(&mut self.pid, &mut game.pid);
+                    swap(&mut self.handle, &mut game.handle);
+                    swap(&mut self.image_path, &mut game.image_path);
+                    swap(&mut self.distribution, &mut game.distribution);
+                    swap(&mut self.mod_indicators, &mut game.mod_indicators);
+                    swap(&mut self.ga_addr, &mut game.ga_addr);
+                    // Offsets set for one build don't carry over to another
+                    if self.branch != game.branch {
+                        swap(&mut self.branch, &mut game.branch);
+                        swap(&mut self.offsets, &mut game.offsets);
+                        swap(&mut self.offsets_known, &mut game.offsets_known);
+                    }
+                    self.invalidate_statics();
+                    self.forget_names();
+                    return Ok(());
+                }
+                Err(e) if timeout.is_some_and(|t| started.elapsed() >= t) => return Err(e),
+                Err(e) => tracing::trace!("waiting for the game: {}", e),
+            }
+
+            sleep(POLL_INTERVAL);
         }
     }
 
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn offsets(&self) -> &OffsetTable {
+        &self.offsets
+    }
+
+    pub fn set_offsets(&mut self, offsets: OffsetTable) {
+        self.offsets = offsets;
+        self.offsets_known = true;
+        self.invalidate_statics();
+    }
+
+    pub fn set_cache_settings(&mut self, cache_settings: CacheSettings) {
+        self.cache_settings = cache_settings;
+        self.invalidate_statics();
+        self.forget_names();
+    }
+
+    pub fn set_string_decoding(&mut self, string_decoding: StringDecoding) {
+        self.string_decoding = string_decoding;
+    }
+
+    pub fn set_consistency(&mut self, consistency: Consistency) {
+        self.consistency = consistency;
+    }
+
+    // For state() and state_with_warnings(); with names, extra roles and a full
+    // lobby, reading players one at a time is most of a poll. The players come
+    // back in the same order either way. state_into() always reads them in
+    // turn, since it's for keeping polls cheap rather than quick. The threads
+    // are started here and kept until the Game is dropped or this is called
+    // again.
+    pub fn set_player_threads(&mut self, threads: usize) {
+        self.player_threads = threads.max(1);
+        self.player_pool = match self.player_threads {
+            1 => None,
+            threads => Some(PlayerPool::new(threads)),
+        };
+    }
+
+    pub fn set_read_options(&mut self, read_options: StateReadOptions) {
+        self.read_options = read_options;
+    }
+
+    pub fn read_options(&self) -> &StateReadOptions {
+        &self.read_options
+    }
+
+    pub fn read_stats(&self) -> ReadStats {
+        self.counters.snapshot()
+    }
+
+    pub fn reset_read_stats(&self) {
+        self.counters.reset();
+    }
+
+    // Called after every ReadProcessMemory, on whichever thread is reading
+    pub fn set_read_hook(&mut self, read_hook: Option<ReadHook>) {
+        self.read_hook = read_hook;
+    }
+
+    // Fields from every pack are read into Player::extra_roles
+    pub fn add_offset_pack(&mut self, pack: OffsetPack) {
+        self.offset_packs.push(pack);
+    }
+
     pub fn state(&self) -> Result<State> {
+        self.read_state(&mut Warnings(None), |list_addr, warnings| unsafe {
+            self.read_players(list_addr, warnings)
+        })
+    }
+
+    pub fn state_with_warnings(&self) -> Result<(State, Vec<Warning>)> {
+        let mut warnings = Warnings(Some(Vec::new()));
+        let state = self.read_state(&mut warnings, |list_addr, warnings| unsafe {
+            self.read_players(list_addr, warnings)
+        })?;
+
+        Ok((state, warnings.0.unwrap_or_default()))
+    }
+
+    // Same as state(), but reads into the players, names and scratch space
+    // from the previous poll instead of allocating new ones
+    pub fn state_into(&self, buffers: &mut StateBuffers) -> Result<()> {
+        let mut players = match &mut buffers.state {
+            State::Menu => take(&mut buffers.spare_players),
+            State::Lobby { players } | State::InGame { players, .. } | State::Ended { players } => {
+                take(players)
+            }
+        };
+        buffers.state = State::Menu;
+
+        let StateBuffers {
+            player_addrs,
+            utf16,
+            ..
+        } = buffers;
+
+        let state = self.read_state(&mut Warnings(None), |list_addr, warnings| unsafe {
+            self.read_players_into(list_addr, take(&mut players), player_addrs, utf16, warnings)
+        });
+
+        // Still holds the old players if this state didn't need them
+        buffers.spare_players = players;
+        buffers.state = state?;
+
+        Ok(())
+    }
+
+    // A failed read may mean a cached address has gone stale, so the next poll
+    // resolves everything again
+    fn read_state(
+        &self,
+        warnings: &mut Warnings,
+        mut read_players: impl FnMut(GameUSize, &mut Warnings) -> Result<Vec<Player>>,
+    ) -> Result<State> {
+        let _span = tracing::trace_span!("state", pid = self.pid).entered();
+
+        let started = Instant::now();
+        let state = match self.consistency {
+            Consistency::Unchecked => self.read_state_uncached(warnings, &mut read_players),
+            Consistency::Verified { retries } => {
+                self.read_state_verified(retries, warnings, &mut read_players)
+            }
+        };
+        self.counters.record_state(started.elapsed());
+
+        if state.is_err() {
+            self.invalidate_statics();
+        }
+
+        state
+    }
+
+    // Just the client state, a read or two once the statics are cached, for
+    // polling cheaply until there's something worth a full state
+    pub fn phase(&self) -> Result<Phase> {
+        let _span = tracing::trace_span!("phase", pid = self.pid).entered();
+
+        let phase = self
+            .read_client_state()
+            .map(|internal_state| match internal_state {
+                InternalState::NotJoined => Phase::Menu,
+                InternalState::Joined => Phase::Lobby,
+                InternalState::Started => Phase::InGame,
+                InternalState::Ended => Phase::Ended,
+            });
+
+        if phase.is_err() {
+            self.invalidate_statics();
+        }
+
+        phase
+    }
+
+    // Handles to the current players, without reading their names or extra
+    // roles; empty while in the menu
+    pub fn player_refs(&self) -> Result<Vec<PlayerRef>> {
+        let player_refs = self.read_player_refs_uncached();
+
+        if player_refs.is_err() {
+            self.invalidate_statics();
+        }
+
+        player_refs
+    }
+
+    fn read_player_refs_uncached(&self) -> Result<Vec<PlayerRef>> {
+        let _span = tracing::trace_span!("player_refs", pid = self.pid).entered();
+
+        if let InternalState::NotJoined = self.read_client_state()? {
+            return Ok(Vec::new());
+        }
+
+        let header = self.read_header()?;
+
+        unsafe { self.read_player_refs(header.player_list_addr) }
+            .and_then(|player_refs| player_refs.into_iter().collect())
+            .map_err(|e| e.within(PlayerManager::NAME))
+    }
+
+    fn read_client_state(&self) -> Result<InternalState> {
+        if let (false, Some(branch)) = (self.offsets_known, &self.branch) {
+            return Err(Error::UnsupportedBeta(branch.clone()));
+        }
+
+        let client_state_addr = self.get_instance_addr::<ClientState>()?;
+
+        let internal_state = unsafe { self.read_internal_state(client_state_addr) }
+            .map_err(|e| e.within(ClientState::NAME))?;
+
+        // Joining or leaving a game is when singletons get torn down and
+        // recreated
+        let previous = self
+            .last_internal_state
+            .swap(internal_state as u32, Ordering::Relaxed);
+        if previous != internal_state as u32 {
+            self.invalidate_statics();
+            self.forget_names();
+        }
+
+        Ok(internal_state)
+    }
+
+    fn read_state_verified(
+        &self,
+        retries: u32,
+        warnings: &mut Warnings,
+        read_players: &mut impl FnMut(GameUSize, &mut Warnings) -> Result<Vec<Player>>,
+    ) -> Result<State> {
+        for attempt in 0..=retries {
+            let before = self.read_shape()?;
+            let kept = warnings.0.as_ref().map_or(0, Vec::len);
+
+            let state = self.read_state_uncached(warnings, &mut *read_players)?;
+
+            if self.read_shape()? == before {
+                return Ok(state);
+            }
+
+            // Warnings from a torn read are about a state nobody will see
+            if let Some(warnings) = &mut warnings.0 {
+                warnings.truncate(kept);
+            }
+            self.counters.record_torn_state();
+            tracing::debug!("state changed while it was read, attempt {}", attempt + 1);
+        }
+
+        Err(ParseError::Unstable {
+            attempts: retries + 1,
+        }
+        .into())
+    }
+
+    // What the rest of the state hangs off, for telling whether it moved
+    fn read_shape(&self) -> Result<StateShape> {
         let client_state_addr = self.get_instance_addr::<ClientState>()?;
+        let internal_state = unsafe {
+            self.read_value::<u32>(
+                client_state_addr + self.offsets.internal_state,
+                "internal state",
+            )
+        }
+        .map_err(|e| e.within(ClientState::NAME))?;
 
-        let internal_state = unsafe { self.read_internal_state(client_state_addr) }?;
+        if let Ok(InternalState::NotJoined) = InternalState::try_from(internal_state) {
+            return Ok(StateShape {
+                internal_state,
+                ..StateShape::default()
+            });
+        }
 
-        match internal_state {
+        let header = self.read_header()?;
+        let [_, player_count] = unsafe {
+            self.read_value::<[GameUSize; 2]>(
+                header.player_list_addr + PLAYER_LIST_ITEMS_OFFSET,
+                "player list",
+            )
+        }
+        .map_err(|e| e.within(PlayerManager::NAME))?;
+
+        Ok(StateShape {
+            internal_state,
+            player_list_addr: header.player_list_addr,
+            player_count,
+            tasks_total: header.tasks_total,
+        })
+    }
+
+    fn read_state_uncached(
+        &self,
+        warnings: &mut Warnings,
+        read_players: impl FnOnce(GameUSize, &mut Warnings) -> Result<Vec<Player>>,
+    ) -> Result<State> {
+        match self.read_client_state()? {
             InternalState::NotJoined => Ok(State::Menu),
-            InternalState::Joined | InternalState::Ended => {
-                let player_manager_addr = self.get_instance_addr::<PlayerManager>()?;
-                let players = unsafe { self.read_players(player_manager_addr) }?;
-                Ok(State::Lobby { players })
+            internal_state @ (InternalState::Joined | InternalState::Ended) => {
+                let players = if self.read_options.players {
+                    let header = self.read_header()?;
+                    read_players(header.player_list_addr, warnings)
+                        .map_err(|e| e.within(PlayerManager::NAME))?
+                } else {
+                    Vec::new()
+                };
+
+                Ok(match internal_state {
+                    InternalState::Ended => State::Ended { players },
+                    _ => State::Lobby { players },
+                })
             }
             InternalState::Started => {
-                let player_manager_addr = self.get_instance_addr::<PlayerManager>()?;
+                let options = &self.read_options;
 
-                let (tasks_total, tasks_completed) =
-                    unsafe { self.read_task_overview(player_manager_addr) }?;
-                let players = unsafe { self.read_players(player_manager_addr) }?;
+                let header = if options.players || options.tasks {
+                    Some(self.read_header()?)
+                } else {
+                    None
+                };
+
+                let (tasks_total, tasks_completed) = match &header {
+                    Some(header) if options.tasks => {
+                        let task_overview = check_plausible(
+                            "tasks completed",
+                            header.tasks_completed,
+                            header.tasks_completed <= header.tasks_total,
+                        )
+                        .map(|_| (header.tasks_total, header.tasks_completed));
+                        warnings.tolerate("task overview", task_overview, || (0, 0))?
+                    }
+                    _ => (0, 0),
+                };
 
-                let meeting_screen_addr = self.get_instance_addr::<MeetingScreen>()?;
+                let players = match &header {
+                    Some(header) if options.players => {
+                        read_players(header.player_list_addr, warnings)
+                            .map_err(|e| e.within(PlayerManager::NAME))?
+                    }
+                    _ => Vec::new(),
+                };
+
+                let meeting_screen_addr = if options.meeting {
+                    self.get_instance_addr::<MeetingScreen>()?
+                } else {
+                    0
+                };
 
                 let meeting = if meeting_screen_addr != 0 {
-                    unsafe { self.read_meeting_progress(meeting_screen_addr) }?
+                    let meeting = unsafe { self.read_meeting_progress(meeting_screen_addr) }
+                        .map_err(|e| e.within(MeetingScreen::NAME));
+                    warnings.tolerate("meeting state", meeting, || MeetingState::Proceeding)?
                 } else {
                     tracing::trace!("meeting_screen nullptr -> assuming proceeding");
                     MeetingState::Proceeding
@@ -176,106 +936,253 @@ impl Game {
         }
     }
 
-    unsafe fn read_internal_state(&self, client_state_addr: GameUSize) -> Result<InternalState> {
-        const INTERNAL_STATE_OFFSET: GameUSize = 0x70;
-
-        let mut internal_state = MaybeUninit::<InternalState>::uninit();
-        let mut count = 0;
+    fn read_header(&self) -> Result<GameDataHeader> {
+        let player_manager_addr = self.get_instance_addr::<PlayerManager>()?;
 
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (client_state_addr + INTERNAL_STATE_OFFSET) as *mut c_void,
-            internal_state.as_mut_ptr() as *mut c_void,
-            size_of::<InternalState>(),
-            &mut count,
-        );
+        unsafe { self.read_player_manager(player_manager_addr) }
+            .map_err(|e| e.within(PlayerManager::NAME))
+    }
 
-        if read_result == 0 {
-            return Err(Error::ReadError(GetLastError(), count, "internal state").into());
-        }
+    unsafe fn read_internal_state(&self, client_state_addr: GameUSize) -> Result<InternalState> {
+        self.read_value::<u32>(
+            client_state_addr + self.offsets.internal_state,
+            "internal state",
+        )?
+        .try_into()
+    }
 
-        Ok(internal_state.assume_init())
+    // The player list pointer is immediately followed by the task totals, so a
+    // single read covers all three
+    unsafe fn read_player_manager(&self, player_manager_addr: GameUSize) -> Result<GameDataHeader> {
+        let [player_list_addr, tasks_total, tasks_completed] = self.read_value::<[GameUSize; 3]>(
+            player_manager_addr + self.offsets.player_list,
+            "player manager",
+        )?;
+
+        Ok(GameDataHeader {
+            player_list_addr,
+            tasks_total,
+            tasks_completed,
+        })
     }
 
-    unsafe fn read_players(&self, player_manager_addr: GameUSize) -> Result<Vec<Player>> {
-        const PLAYER_LIST_PTR_OFFSET: GameUSize = 0x24;
-        const PLAYER_LIST_SIZE_OFFSET: GameUSize = 0xC;
-        const PLAYER_LIST_FIRST_OBJECT: GameUSize = 0x8;
-        const PLAYER_ARRAY_OFFSET: GameUSize = 0x10;
+    unsafe fn read_players(
+        &self,
+        player_list_addr: GameUSize,
+        warnings: &mut Warnings,
+    ) -> Result<Vec<Player>> {
+        let mut player_refs = self.read_player_refs(player_list_addr)?;
+
+        let threads = self.player_threads.min(player_refs.len());
+        let pool = match &self.player_pool {
+            Some(pool) if threads > 1 => pool,
+            _ => return self.read_player_run(player_refs, warnings),
+        };
 
-        let player_list_addr =
-            self.read_game_usize(player_manager_addr + PLAYER_LIST_PTR_OFFSET)?;
+        // Contiguous runs, one per thread, so joining them in order keeps the
+        // list's order
+        let run_len = player_refs.len().div_ceil(threads);
+        let mut runs = Vec::with_capacity(threads);
+        while !player_refs.is_empty() {
+            let end = run_len.min(player_refs.len());
+            runs.push(player_refs.drain(..end).collect::<Vec<_>>());
+        }
 
-        let mut player_count = MaybeUninit::<GameUSize>::uninit();
-        let mut count = 0;
+        let run_count = runs.len();
+        let (sender, receiver) = crossbeam_channel::bounded(run_count);
+
+        for (index, run) in runs.into_iter().enumerate() {
+            let game = GameRef(self);
+            let sender = sender.clone();
+            let mut run_warnings = Warnings(warnings.0.as_ref().map(|_| Vec::new()));
+
+            pool.run(Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    // Safe while this function is still waiting on the run
+                    let game = unsafe { game.get() };
+                    let players = unsafe { game.read_player_run(run, &mut run_warnings) };
+                    players.map(|players| (players, run_warnings))
+                }));
+                let _ = sender.send((index, result));
+            }));
+        }
+        drop(sender);
 
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (player_list_addr + PLAYER_LIST_SIZE_OFFSET) as *mut c_void,
-            player_count.as_mut_ptr() as *mut c_void,
-            size_of::<GameUSize>(),
-            &mut count,
-        );
+        // Every job holds a sender, so this waits until they've all finished
+        // with the Game
+        let mut results: Vec<_> = receiver.iter().collect();
+        results.sort_by_key(|(index, _)| *index);
+        assert_eq!(results.len(), run_count, "a player reader thread has gone");
 
-        if read_result == 0 {
-            return Err(Error::ReadError(GetLastError(), count, "player list size").into());
+        let mut players = Vec::new();
+        for (_, result) in results {
+            let (run, run_warnings) = result.unwrap_or_else(|panic| panic::resume_unwind(panic))?;
+            players.extend(run);
+
+            if let (Some(warnings), Some(run_warnings)) = (&mut warnings.0, run_warnings.0) {
+                warnings.extend(run_warnings);
+            }
         }
 
-        let player_count = player_count.assume_init();
+        Ok(players)
+    }
 
-        let first_player_addr = self
-            .read_game_usize(player_list_addr + PLAYER_LIST_FIRST_OBJECT)?
-            + PLAYER_ARRAY_OFFSET;
+    unsafe fn read_player_run(
+        &self,
+        player_refs: Vec<Result<PlayerRef>>,
+        warnings: &mut Warnings,
+    ) -> Result<Vec<Player>> {
+        let mut players = Vec::with_capacity(player_refs.len());
+
+        for player_ref in player_refs {
+            let player = player_ref
+                .and_then(|player_ref| self.read_player(player_ref, warnings))
+                .map(Some);
+
+            if let Some(player) = warnings.tolerate("player", player, || None)? {
+                players.push(player);
+            }
+        }
 
-        let mut players = Vec::with_capacity(player_count as usize);
+        Ok(players)
+    }
 
-        for idx in 0..player_count {
-            let player_addr = self
-                .read_game_usize(first_player_addr + idx * size_of::<GameUSize>() as GameUSize)?;
+    // Only a failure to read the list itself is an error here; each player
+    // gets its own result
+    unsafe fn read_players_into(
+        &self,
+        player_list_addr: GameUSize,
+        mut players: Vec<Player>,
+        player_addrs: &mut Vec<GameUSize>,
+        utf16: &mut Vec<u16>,
+        warnings: &mut Warnings,
+    ) -> Result<Vec<Player>> {
+        self.read_player_addrs(player_list_addr, player_addrs)?;
+
+        let mut len = 0;
+
+        // Struct-sized reads straight onto the stack; the read planner would
+        // save syscalls but allocates its blocks
+        for &player_addr in player_addrs.iter() {
+            let player = self
+                .read_value::<[u8; PLAYER_STRUCT_SIZE]>(
+                    player_addr + PLAYER_FIELDS_OFFSET,
+                    "raw player",
+                )
+                .and_then(|raw_bytes| self.parse_player(player_addr, &raw_bytes))
+                .and_then(|player_ref| match players.get_mut(len) {
+                    Some(player) => self.fill_player(player, player_ref, utf16, warnings),
+                    None => {
+                        players.push(self.read_player(player_ref, warnings)?);
+                        Ok(())
+                    }
+                })
+                .map(Some);
 
-            players.push(self.read_player(player_addr)?);
+            if warnings.tolerate("player", player, || None)?.is_some() {
+                len += 1;
+            }
         }
 
+        players.truncate(len);
+
         Ok(players)
     }
 
-    unsafe fn read_player(&self, player_addr: GameUSize) -> Result<Player> {
-        const PLAYER_STRUCT_SIZE: usize = 0x2C;
-        let mut raw_bytes: Vec<u8> = Vec::with_capacity(PLAYER_STRUCT_SIZE);
-        let mut count = 0;
+    unsafe fn read_player_addrs(
+        &self,
+        player_list_addr: GameUSize,
+        player_addrs: &mut Vec<GameUSize>,
+    ) -> Result<()> {
+        const PLAYER_ARRAY_OFFSET: GameUSize = 0x10;
 
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (player_addr + 8) as *mut c_void, // + 8 to skip klass/monitor fields
-            raw_bytes.as_mut_ptr() as *mut c_void,
-            PLAYER_STRUCT_SIZE,
-            &mut count,
-        );
+        let span = tracing::trace_span!(
+            "player_list",
+            addr = %Hex(player_list_addr),
+            count = tracing::field::Empty,
+        )
+        .entered();
+
+        let [items_addr, player_count] = self.read_value::<[GameUSize; 2]>(
+            player_list_addr + PLAYER_LIST_ITEMS_OFFSET,
+            "player list",
+        )?;
+        span.record("count", player_count);
+        check_plausible("player count", player_count, player_count <= MAX_PLAYERS)?;
+
+        self.read_vec_into(
+            items_addr + PLAYER_ARRAY_OFFSET,
+            player_count as usize,
+            "player pointers",
+            player_addrs,
+        )
+    }
 
-        if read_result == 0 || count != PLAYER_STRUCT_SIZE {
-            return Err(Error::ReadError(GetLastError(), count, "raw player").into());
+    unsafe fn read_player_refs(
+        &self,
+        player_list_addr: GameUSize,
+    ) -> Result<Vec<Result<PlayerRef>>> {
+        let mut player_addrs = Vec::new();
+        self.read_player_addrs(player_list_addr, &mut player_addrs)?;
+
+        // PlayerInfo objects are allocated together when players join, so the
+        // structs usually come back in one or two reads
+        let mut plan = ReadPlan::with_max_gap(PLAYER_STRUCT_GAP);
+        for &player_addr in &player_addrs {
+            plan.add(
+                (player_addr + PLAYER_FIELDS_OFFSET) as usize,
+                PLAYER_STRUCT_SIZE,
+            );
         }
+        let gathered =
+            plan.execute(|address, len| self.read_vec(address as GameUSize, len, "player structs"));
+
+        let player_refs = player_addrs
+            .into_iter()
+            .map(|player_addr| {
+                let fields_addr = player_addr + PLAYER_FIELDS_OFFSET;
+                let raw_bytes = match gathered.bytes(fields_addr as usize, PLAYER_STRUCT_SIZE) {
+                    Some(raw_bytes) => Cow::Borrowed(raw_bytes),
+                    None => {
+                        Cow::Owned(self.read_vec(fields_addr, PLAYER_STRUCT_SIZE, "raw player")?)
+                    }
+                };
+
+                self.parse_player(player_addr, &raw_bytes)
+            })
+            .collect();
 
-        raw_bytes.set_len(count);
+        Ok(player_refs)
+    }
 
+    fn parse_player(&self, player_addr: GameUSize, raw_bytes: &[u8]) -> Result<PlayerRef> {
         let id = raw_bytes[0];
         let name_addr = u32::from_ne_bytes(raw_bytes[4..8].try_into()?);
         let _dont_censor_name = raw_bytes[8] != 0;
-        let colour = i32::from_ne_bytes(raw_bytes[12..16].try_into()?);
-        let hat = u32::from_ne_bytes(raw_bytes[16..20].try_into()?);
-        let pet = u32::from_ne_bytes(raw_bytes[20..24].try_into()?);
-        let skin = u32::from_ne_bytes(raw_bytes[24..28].try_into()?);
+        let colour_id = i32::from_ne_bytes(raw_bytes[12..16].try_into()?);
+        // Colour ids go over the network as a byte; newer colours than this
+        // release knows come back as Colour::Unknown
+        check_plausible("colour", colour_id, (0..=255).contains(&colour_id))?;
+        let colour = Colour::from(colour_id);
+        let (hat, pet, skin) = if self.read_options.cosmetics {
+            (
+                u32::from_ne_bytes(raw_bytes[16..20].try_into()?).into(),
+                u32::from_ne_bytes(raw_bytes[20..24].try_into()?).into(),
+                u32::from_ne_bytes(raw_bytes[24..28].try_into()?).into(),
+            )
+        } else {
+            (Hat::None, Pet::None, Skin::None)
+        };
         let disconnected = raw_bytes[28] != 0;
         let tasks_addr = u32::from_ne_bytes(raw_bytes[32..36].try_into()?);
         let impostor = raw_bytes[36] != 0;
         let dead = raw_bytes[37] != 0;
         let game_object_addr = u32::from_ne_bytes(raw_bytes[40..44].try_into()?);
 
-        let name = self.read_string(name_addr)?;
-
-        Ok(Player {
+        Ok(PlayerRef {
+            addr: player_addr,
+            name_addr,
             id,
-            name,
             colour,
             hat,
             pet,
@@ -288,121 +1195,712 @@ impl Game {
         })
     }
 
-    unsafe fn read_task_overview(
+    unsafe fn read_player(&self, player_ref: PlayerRef, warnings: &mut Warnings) -> Result<Player> {
+        let mut player = Player {
+            id: player_ref.id,
+            name: String::new(),
+            colour: player_ref.colour,
+            hat: player_ref.hat,
+            pet: player_ref.pet,
+            skin: player_ref.skin,
+            disconnected: player_ref.disconnected,
+            tasks_addr: player_ref.tasks_addr,
+            impostor: player_ref.impostor,
+            dead: player_ref.dead,
+            game_object_addr: player_ref.game_object_addr,
+            extra_roles: HashMap::new(),
+        };
+
+        self.fill_player(&mut player, player_ref, &mut Vec::new(), warnings)?;
+
+        Ok(player)
+    }
+
+    // Overwrites an existing player in place, reusing its name and extra role
+    // allocations
+    unsafe fn fill_player(
         &self,
-        player_manager_addr: GameUSize,
-    ) -> Result<(GameUSize, GameUSize)> {
-        const TASKS_OFFSET: GameUSize = 0x28;
-
-        let mut tasks_tuple = MaybeUninit::<(GameUSize, GameUSize)>::uninit();
-        let mut count = 0;
-
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (player_manager_addr + TASKS_OFFSET) as *mut c_void,
-            tasks_tuple.as_mut_ptr() as *mut c_void,
-            size_of::<(GameUSize, GameUSize)>(),
-            &mut count,
-        );
+        player: &mut Player,
+        player_ref: PlayerRef,
+        utf16: &mut Vec<u16>,
+        warnings: &mut Warnings,
+    ) -> Result<()> {
+        let _span =
+            tracing::trace_span!("player", id = player_ref.id, addr = %Hex(player_ref.addr))
+                .entered();
+
+        if self.read_options.names {
+            let name = self.read_name_into(player_ref.name_addr, utf16, &mut player.name);
+            warnings.tolerate("player name", name, || player.name.clear())?;
+        } else {
+            player.name.clear();
+        }
+
+        self.read_extra_roles_into(&player_ref, &mut player.extra_roles, warnings)?;
+
+        player.id = player_ref.id;
+        player.colour = player_ref.colour;
+        player.hat = player_ref.hat;
+        player.pet = player_ref.pet;
+        player.skin = player_ref.skin;
+        player.disconnected = player_ref.disconnected;
+        player.tasks_addr = player_ref.tasks_addr;
+        player.impostor = player_ref.impostor;
+        player.dead = player_ref.dead;
+        player.game_object_addr = player_ref.game_object_addr;
+
+        Ok(())
+    }
+
+    unsafe fn read_extra_roles(
+        &self,
+        player_ref: &PlayerRef,
+        warnings: &mut Warnings,
+    ) -> Result<HashMap<String, RoleValue>> {
+        let mut extra_roles = HashMap::new();
+        self.read_extra_roles_into(player_ref, &mut extra_roles, warnings)?;
+
+        Ok(extra_roles)
+    }
 
-        if read_result == 0 {
-            return Err(Error::ReadError(GetLastError(), count, "task overview").into());
+    unsafe fn read_extra_roles_into(
+        &self,
+        player_ref: &PlayerRef,
+        extra_roles: &mut HashMap<String, RoleValue>,
+        warnings: &mut Warnings,
+    ) -> Result<()> {
+        for field in self.offset_packs.iter().flat_map(|pack| &pack.fields) {
+            let base = match field.base {
+                FieldBase::PlayerInfo => player_ref.addr,
+                FieldBase::PlayerControl => player_ref.game_object_addr,
+            };
+
+            let value = self
+                .read_role_value(base + field.offset, field.kind)
+                .map(Some);
+            match warnings.tolerate("extra role", value, || None)? {
+                // Avoids cloning the key when the entry is already there
+                Some(value) => match extra_roles.get_mut(&field.key) {
+                    Some(existing) => *existing = value,
+                    None => {
+                        extra_roles.insert(field.key.clone(), value);
+                    }
+                },
+                None => {
+                    extra_roles.remove(&field.key);
+                }
+            }
         }
 
-        Ok(tasks_tuple.assume_init())
+        Ok(())
+    }
+
+    unsafe fn read_role_value(&self, address: GameUSize, kind: FieldKind) -> Result<RoleValue> {
+        const WHAT: &str = "extra role";
+
+        Ok(match kind {
+            FieldKind::Bool => RoleValue::Bool(self.read_value::<u8>(address, WHAT)? != 0),
+            FieldKind::U8 => RoleValue::Int(self.read_value::<u8>(address, WHAT)?.into()),
+            FieldKind::I32 => RoleValue::Int(self.read_value::<i32>(address, WHAT)?.into()),
+            FieldKind::U32 => RoleValue::Int(self.read_value::<u32>(address, WHAT)?.into()),
+            FieldKind::String => {
+                RoleValue::String(self.read_string(self.read_game_usize(address)?)?)
+            }
+        })
     }
 
     unsafe fn read_meeting_progress(&self, meeting_screen_addr: GameUSize) -> Result<MeetingState> {
-        const MEETING_STATE_OFFSET: GameUSize = 0x74;
+        let _span = tracing::trace_span!("meeting", addr = %Hex(meeting_screen_addr)).entered();
+
+        self.read_value::<u32>(
+            meeting_screen_addr + self.offsets.meeting_state,
+            "meeting state",
+        )?
+        .try_into()
+    }
+
+    unsafe fn read_error(
+        &self,
+        address: GameUSize,
+        requested: usize,
+        bytes: usize,
+        what: &'static str,
+    ) -> Error {
+        let code = GetLastError();
+
+        let mut exit_code = 0;
+        if GetExitCodeProcess(self.handle.0, &mut exit_code) != 0 && exit_code != STILL_ACTIVE {
+            return Error::ProcessGone;
+        }
+
+        ReadError {
+            code,
+            address: address as usize,
+            requested,
+            bytes,
+            what,
+            chain: Vec::new(),
+            modded: self.is_modded(),
+        }
+        .into()
+    }
+
+    fn invalidate_statics(&self) {
+        for slot in &self.statics_cache {
+            slot.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn record_read(
+        &self,
+        address: GameUSize,
+        requested: usize,
+        bytes: usize,
+        what: &'static str,
+        succeeded: bool,
+        started: Option<Instant>,
+    ) {
+        // Taken before tracing or the hook can overwrite it
+        let error = if succeeded {
+            None
+        } else {
+            Some(unsafe { GetLastError() })
+        };
 
-        let mut meeting_state = MaybeUninit::<MeetingState>::uninit();
-        let mut count = 0;
+        self.counters.record_read(bytes, succeeded);
 
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (meeting_screen_addr + MEETING_STATE_OFFSET) as *mut c_void,
-            meeting_state.as_mut_ptr() as *mut c_void,
-            size_of::<MeetingState>(),
-            &mut count,
+        tracing::trace!(
+            what,
+            addr = %Hex(address),
+            requested,
+            bytes,
+            succeeded,
+            "read"
         );
 
-        if read_result == 0 {
-            return Err(Error::ReadError(GetLastError(), count, "meeting state").into());
+        if let (Some(read_hook), Some(started)) = (&self.read_hook, started) {
+            read_hook(&ReadRecord {
+                address: address as usize,
+                requested,
+                bytes,
+                what,
+                duration: started.elapsed(),
+                succeeded,
+                error,
+            });
         }
 
-        Ok(meeting_state.assume_init())
+        // The failed read's error still has to be there for read_error
+        if let Some(error) = error {
+            unsafe { SetLastError(error) };
+        }
     }
 
+    // Only the instance pointer is read every time; it changes whenever the
+    // game recreates the object, e.g. a new MeetingHud per meeting
     fn get_instance_addr<T: InstancedClass>(&self) -> Result<GameUSize> {
-        let class_addr = unsafe { self.read_game_usize(self.ga_addr + T::CLASS_OFFSET) }?;
-        let statics_addr = unsafe { self.read_game_usize(class_addr + T::STATICS_OFFSET) }?;
-        let instance_addr = unsafe { self.read_game_usize(statics_addr + T::INSTANCE_OFFSET) }?;
+        let slot = &self.statics_cache[T::CACHE_SLOT];
+
+        let _span = tracing::trace_span!("instance", class = T::NAME).entered();
+
+        let statics_addr = match slot.load(Ordering::Relaxed) {
+            0 => {
+                let class_addr =
+                    unsafe { self.read_game_usize(self.ga_addr + T::class_offset(&self.offsets)) }
+                        .map_err(|e| e.within("class").within(T::NAME))?;
+                let statics_addr =
+                    unsafe { self.read_game_usize(class_addr + self.offsets.statics) }
+                        .map_err(|e| e.within("statics").within(T::NAME))?;
+                if self.cache_settings.statics {
+                    slot.store(statics_addr, Ordering::Relaxed);
+                }
+                statics_addr
+            }
+            cached => cached,
+        };
+
+        let instance_addr = unsafe { self.read_game_usize(statics_addr + T::INSTANCE_OFFSET) }
+            .map_err(|e| e.within("instance").within(T::NAME))?;
 
         Ok(instance_addr)
     }
 
     unsafe fn read_game_usize(&self, address: GameUSize) -> Result<GameUSize> {
-        let mut ptr = MaybeUninit::<GameUSize>::uninit();
-        let mut count = 0;
-
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            address as *mut c_void,
-            ptr.as_mut_ptr() as *mut c_void,
-            size_of::<GameUSize>(),
-            &mut count,
-        );
+        self.read_value(address, "pointer")
+    }
+
+    // .NET strings are immutable, so renaming a player gives them a new string
+    // object and a new key here. The garbage collector can reuse an address,
+    // so a hit only counts if the length still matches, and the cache is
+    // dropped on every state transition
+    unsafe fn read_name_into(
+        &self,
+        address: GameUSize,
+        utf16: &mut Vec<u16>,
+        name: &mut String,
+    ) -> Result<()> {
+        if !self.cache_settings.names {
+            return self.read_string_into(address, utf16, name);
+        }
+
+        // The lock is only held between reads, so the player pool's threads
+        // don't queue up on it
+        let cached_len = self.lock_names().get(&address).map(|(len, _)| *len);
+
+        if let Some(cached_len) = cached_len {
+            if self.read_game_usize(address + 0x08)? == cached_len {
+                if let Some((len, cached)) = self.lock_names().get(&address) {
+                    if *len == cached_len {
+                        name.clear();
+                        name.push_str(cached);
+                        return Ok(());
+                    }
+                }
+            }
+        }
 
-        if read_result == 0 {
-            return Err(Error::ReadError(GetLastError(), count, "pointer").into());
+        self.read_string_into(address, utf16, name)?;
+
+        let mut cache = self.lock_names();
+        if cache.len() >= NAME_CACHE_SIZE {
+            cache.clear();
         }
+        cache.insert(address, (utf16.len() as GameUSize, name.clone()));
+
+        Ok(())
+    }
 
-        Ok(ptr.assume_init())
+    fn lock_names(&self) -> MutexGuard<'_, HashMap<GameUSize, (GameUSize, String)>> {
+        // Names are only a cache, so one left half-updated by a panic is
+        // thrown away
+        self.name_cache.lock().unwrap_or_else(|poisoned| {
+            let mut cache = poisoned.into_inner();
+            cache.clear();
+            cache
+        })
+    }
+
+    fn forget_names(&self) {
+        self.lock_names().clear();
     }
 
     unsafe fn read_string(&self, address: GameUSize) -> Result<String> {
+        let mut string = String::new();
+        self.read_string_into(address, &mut Vec::new(), &mut string)?;
+
+        Ok(string)
+    }
+
+    unsafe fn read_string_into(
+        &self,
+        address: GameUSize,
+        utf16: &mut Vec<u16>,
+        string: &mut String,
+    ) -> Result<()> {
         let str_len = self.read_game_usize(address + 0x08)?;
-        let mut count = 0;
-
-        let mut str_raw: Vec<u16> = Vec::with_capacity(str_len as usize);
-        let read_result = ReadProcessMemory(
-            self.handle as *mut c_void,
-            (address + 12) as *mut c_void,
-            str_raw.as_mut_ptr() as *mut c_void,
-            str_len as usize * size_of::<u16>(),
-            &mut count,
+        check_plausible("string length", str_len, str_len <= MAX_STRING_LEN)?;
+        self.read_vec_into(address + 12, str_len as usize, "string", utf16)?;
+
+        string.clear();
+
+        for c in char::decode_utf16(utf16.iter().copied()) {
+            match (c, self.string_decoding) {
+                (Ok(c), _) => string.push(c),
+                (Err(_), StringDecoding::Lossy) => string.push(char::REPLACEMENT_CHARACTER),
+                // Let from_utf16 build the usual error for the same input
+                (Err(_), StringDecoding::Strict) => {
+                    String::from_utf16(utf16)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn read_value<T>(&self, address: GameUSize, what: &'static str) -> Result<T> {
+        self.retry_policy.run(|| {
+            let mut value = MaybeUninit::<T>::uninit();
+            let mut count = 0;
+
+            let started = self.read_hook.as_ref().map(|_| Instant::now());
+            let read_result = ReadProcessMemory(
+                self.handle.0,
+                address as *mut c_void,
+                value.as_mut_ptr() as *mut c_void,
+                size_of::<T>(),
+                &mut count,
+            );
+            self.record_read(
+                address,
+                size_of::<T>(),
+                count,
+                what,
+                read_result != 0,
+                started,
+            );
+
+            if read_result == 0 {
+                return Err(self.read_error(address, size_of::<T>(), count, what));
+            }
+
+            Ok(value.assume_init())
+        })
+    }
+
+    unsafe fn read_vec<T>(
+        &self,
+        address: GameUSize,
+        len: usize,
+        what: &'static str,
+    ) -> Result<Vec<T>> {
+        let mut values = Vec::with_capacity(len);
+        self.read_vec_into(address, len, what, &mut values)?;
+
+        Ok(values)
+    }
+
+    // Replaces the contents of values, keeping its allocation when it's big
+    // enough
+    unsafe fn read_vec_into<T>(
+        &self,
+        address: GameUSize,
+        len: usize,
+        what: &'static str,
+        values: &mut Vec<T>,
+    ) -> Result<()> {
+        self.retry_policy.run(|| {
+            values.clear();
+            values.reserve(len);
+            let mut count = 0;
+
+            let started = self.read_hook.as_ref().map(|_| Instant::now());
+            let read_result = ReadProcessMemory(
+                self.handle.0,
+                address as *mut c_void,
+                values.as_mut_ptr() as *mut c_void,
+                len * size_of::<T>(),
+                &mut count,
+            );
+            let succeeded = read_result != 0 && count == len * size_of::<T>();
+            self.record_read(
+                address,
+                len * size_of::<T>(),
+                count,
+                what,
+                succeeded,
+                started,
+            );
+
+            if !succeeded {
+                return Err(self.read_error(address, len * size_of::<T>(), count, what));
+            }
+
+            values.set_len(len);
+
+            Ok(())
+        })
+    }
+}
+
+impl StateSource for Game {
+    fn state(&self) -> Result<State> {
+        Game::state(self)
+    }
+}
+
+// Process handles may be used from any thread, and every call made through
+// this one (ReadProcessMemory, GetExitCodeProcess) only reads
+struct ProcessHandle(HANDLE);
+
+unsafe impl Send for ProcessHandle {}
+unsafe impl Sync for ProcessHandle {}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+// Threads kept for the life of the Game to read runs of players on
+struct PlayerPool {
+    jobs: Option<Sender<PlayerJob>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+type PlayerJob = Box<dyn FnOnce() + Send>;
+
+impl PlayerPool {
+    fn new(threads: usize) -> Self {
+        let (jobs, receiver) = crossbeam_channel::unbounded::<PlayerJob>();
+
+        let threads = (0..threads)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    for job in receiver {
+                        job();
+                    }
+                })
+            })
+            .collect();
+
+        PlayerPool {
+            jobs: Some(jobs),
+            threads,
+        }
+    }
+
+    fn run(&self, job: PlayerJob) {
+        if let Some(jobs) = &self.jobs {
+            // Only fails once every thread has gone, which the caller notices
+            // from the missing result
+            let _ = jobs.send(job);
+        }
+    }
+}
+
+impl Drop for PlayerPool {
+    fn drop(&mut self) {
+        // Closing the channel ends each thread's loop
+        self.jobs = None;
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Lends the Game to a pool thread; read_players doesn't return until every
+// job it handed out has sent its result, so the borrow outlives them
+struct GameRef(*const Game);
+
+unsafe impl Send for GameRef {}
+
+impl GameRef {
+    unsafe fn get<'a>(self) -> &'a Game {
+        &*self.0
+    }
+}
+
+// Game is shared between threads by GameWatcher's deadlines; keep it that way
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Game>();
+};
+
+// Async wrappers which run the blocking Win32 calls on tokio's blocking pool
+#[cfg(feature = "tokio")]
+impl Game {
+    pub async fn from_pid_async(pid: usize, access: AccessMode) -> Result<Self> {
+        blocking(move || Self::from_pid_with_access(pid, access)).await
+    }
+
+    pub async fn find_async(access: AccessMode) -> Result<Self> {
+        blocking(move || Self::find_with_access(access)).await
+    }
+
+    pub async fn state_async(self: &std::sync::Arc<Self>) -> Result<State> {
+        let game = std::sync::Arc::clone(self);
+        blocking(move || game.state()).await
+    }
+
+    pub async fn state_with_warnings_async(
+        self: &std::sync::Arc<Self>,
+    ) -> Result<(State, Vec<Warning>)> {
+        let game = std::sync::Arc::clone(self);
+        blocking(move || game.state_with_warnings()).await
+    }
+}
+
+// Panics on the blocking pool carry on in the caller; the task can only be
+// cancelled by the runtime shutting down
+#[cfg(feature = "tokio")]
+async fn blocking<T: Send + 'static>(
+    read: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    match tokio::task::spawn_blocking(read).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(_) => Err(Error::Cancelled),
+    }
+}
+
+// Checks the process really is Among Us before locating GameAssembly.dll; any
+// il2cpp game has one
+unsafe fn inspect(
+    handle: HANDLE,
+    pid: usize,
+    access: AccessMode,
+    module: &ModuleMatch,
+) -> Result<(PathBuf, GameUSize)> {
+    let image_path = process::image_path(handle)?;
+
+    let executable = image_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if !executable.eq_ignore_ascii_case(process::GAME_EXECUTABLE) {
+        return Err(AttachError::NotAmongUs { executable }.into());
+    }
+
+    let ga_addr = match access {
+        AccessMode::Full => find_game_assembly(handle, module)?,
+        AccessMode::Limited => find_game_assembly_snapshot(pid, module)?,
+    };
+
+    Ok((image_path, ga_addr))
+}
+
+unsafe fn find_game_assembly(handle: HANDLE, module: &ModuleMatch) -> Result<GameUSize> {
+    // Store builds load a lot more than Steam ones; the list grows to fit
+    const INITIAL_MODULE_COUNT: usize = 128;
+    const MAX_MODULE_NAME_LEN: usize = 64;
+
+    let mut modules: Vec<HMODULE> = vec![null_mut(); INITIAL_MODULE_COUNT];
+
+    loop {
+        let mut count_bytes = 0;
+
+        let enum_modules_result = EnumProcessModulesEx(
+            handle,
+            modules.as_mut_ptr(),
+            (size_of::<HMODULE>() * modules.len()) as u32,
+            &mut count_bytes,
+            0x03, // Get both 32- and 64-bit modules
         );
 
-        if read_result == 0 || count / size_of::<u16>() != str_len as usize {
-            return Err(Error::ReadError(GetLastError(), count, "string").into());
+        if enum_modules_result == 0 {
+            return Err(AttachError::EnumModules(GetLastError()).into());
+        }
+
+        let count = count_bytes as usize / size_of::<HMODULE>();
+        if count <= modules.len() {
+            modules.truncate(count);
+            break;
+        }
+
+        modules.resize(count, null_mut());
+    }
+
+    let ga_addr = modules.into_iter().find_map(|hm| {
+        let mut mod_name: Vec<u16> = Vec::with_capacity(MAX_MODULE_NAME_LEN);
+
+        let len = GetModuleBaseNameW(
+            handle,
+            hm,
+            mod_name.as_mut_ptr(),
+            MAX_MODULE_NAME_LEN as u32,
+        ) as usize;
+
+        mod_name.set_len(len);
+
+        if !module.matches(&String::from_utf16(&mod_name).ok()?) {
+            return None;
+        }
+
+        Some(hm)
+    });
+
+    ga_addr
+        .map(|addr| addr as GameUSize)
+        .ok_or_else(|| AttachError::MissingGameAssembly.into())
+}
+
+// Full access first, then limited access if the failure looks like the sort
+// Store and Game Pass installs give with full access
+pub(crate) fn with_access_fallback(attach: impl Fn(AccessMode) -> Result<Game>) -> Result<Game> {
+    attach(AccessMode::Full).or_else(|e| {
+        if !needs_limited_access(&e) {
+            return Err(e);
+        }
+
+        tracing::debug!("retrying with limited access: {}", e);
+        attach(AccessMode::Limited)
+    })
+}
+
+fn needs_limited_access(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Attach(
+            AttachError::AccessDenied
+                | AttachError::EnumModules(_)
+                | AttachError::MissingGameAssembly
+        )
+    )
+}
+
+// A toolhelp snapshot lists modules without needing PROCESS_QUERY_INFORMATION
+// on our own handle
+fn find_game_assembly_snapshot(pid: usize, module: &ModuleMatch) -> Result<GameUSize> {
+    process::modules(pid)?
+        .into_iter()
+        .find(|found| module.matches(&found.name))
+        .map(|module| module.base as GameUSize)
+        .ok_or_else(|| AttachError::MissingGameAssembly.into())
+}
+
+// Values read through a torn or stale pointer chain are often garbage; catch
+// them before they turn into huge allocations or nonsense state
+fn check_plausible(field: &'static str, value: impl Into<i64>, plausible: bool) -> Result<()> {
+    if plausible {
+        Ok(())
+    } else {
+        Err(ParseError::Implausible {
+            field,
+            value: value.into(),
         }
+        .into())
+    }
+}
 
-        str_raw.set_len(str_len as usize);
+// Addresses in trace output, formatted the same as in ReadError
+struct Hex(GameUSize);
 
-        Ok(String::from_utf16(&str_raw)?)
+impl Display for Hex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:#010x}", self.0)
     }
 }
 
 trait InstancedClass {
-    const CLASS_OFFSET: GameUSize;
-    const STATICS_OFFSET: GameUSize = 0x5C;
+    const NAME: &'static str;
+    const CACHE_SLOT: usize;
     const INSTANCE_OFFSET: GameUSize = 0x00;
+
+    fn class_offset(offsets: &OffsetTable) -> GameUSize;
 }
 
 struct ClientState {}
 
 impl InstancedClass for ClientState {
-    const CLASS_OFFSET: GameUSize = 0x01BAA960; // AmongUsClient
+    const NAME: &'static str = "AmongUsClient";
+    const CACHE_SLOT: usize = 0;
+
+    fn class_offset(offsets: &OffsetTable) -> GameUSize {
+        offsets.client_state_class
+    }
 }
 
 struct PlayerManager {}
 
 impl InstancedClass for PlayerManager {
-    const CLASS_OFFSET: GameUSize = 0x01BC2EAC; // GameData
+    const NAME: &'static str = "GameData";
+    const CACHE_SLOT: usize = 1;
+
+    fn class_offset(offsets: &OffsetTable) -> GameUSize {
+        offsets.player_manager_class
+    }
 }
 
 struct MeetingScreen {}
 
 impl InstancedClass for MeetingScreen {
-    const CLASS_OFFSET: GameUSize = 0x01B9F7A0; // MeetingHud
+    const NAME: &'static str = "MeetingHud";
+    const CACHE_SLOT: usize = 2;
+
+    fn class_offset(offsets: &OffsetTable) -> GameUSize {
+        offsets.meeting_screen_class
+    }
 }
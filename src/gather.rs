@@ -0,0 +1,162 @@
+use crate::Result;
+
+// Collects the ranges a snapshot needs so overlapping or adjacent ones can be
+// fetched with a single read
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReadPlan {
+    ranges: Vec<(usize, usize)>,
+    max_gap: usize,
+}
+
+impl ReadPlan {
+    // Ranges up to max_gap bytes apart are merged too, reading the gap along
+    // with them; worth it when the objects are known to sit close together
+    pub(crate) fn with_max_gap(max_gap: usize) -> Self {
+        ReadPlan {
+            ranges: Vec::new(),
+            max_gap,
+        }
+    }
+
+    pub(crate) fn add(&mut self, address: usize, len: usize) {
+        if len > 0 {
+            self.ranges.push((address, address + len));
+        }
+    }
+
+    // Merged (start, end) ranges in address order
+    fn blocks(&self) -> Vec<(usize, usize)> {
+        let mut ranges = self.ranges.clone();
+        ranges.sort_unstable();
+
+        let mut blocks: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+
+        for (start, end) in ranges {
+            match blocks.last_mut() {
+                Some((_, last_end)) if start <= last_end.saturating_add(self.max_gap) => {
+                    *last_end = end.max(*last_end)
+                }
+                _ => blocks.push((start, end)),
+            }
+        }
+
+        blocks
+    }
+
+    // A block that fails to read is left out rather than failing the whole
+    // plan; lookups into it miss and the caller falls back to a direct read,
+    // which reports the error for just that field
+    pub(crate) fn execute(
+        &self,
+        mut read: impl FnMut(usize, usize) -> Result<Vec<u8>>,
+    ) -> Gathered {
+        let blocks = self
+            .blocks()
+            .into_iter()
+            .filter_map(|(start, end)| match read(start, end - start) {
+                Ok(bytes) => Some((start, bytes)),
+                Err(e) => {
+                    tracing::trace!("gathered read at {:#010x} failed: {}", start, e);
+                    None
+                }
+            })
+            .collect();
+
+        Gathered { blocks }
+    }
+}
+
+// Local copies of the blocks a plan read, ordered by address
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Gathered {
+    blocks: Vec<(usize, Vec<u8>)>,
+}
+
+impl Gathered {
+    pub(crate) fn bytes(&self, address: usize, len: usize) -> Option<&[u8]> {
+        let idx = match self
+            .blocks
+            .binary_search_by_key(&address, |(start, _)| *start)
+        {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+
+        let (start, bytes) = &self.blocks[idx];
+        let offset = address - start;
+
+        bytes.get(offset..offset.checked_add(len)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    // Memory where every byte is the low byte of its address
+    fn read(address: usize, len: usize) -> Result<Vec<u8>> {
+        Ok((address..address + len).map(|a| a as u8).collect())
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        let mut plan = ReadPlan::default();
+        plan.add(0x110, 8);
+        plan.add(0x100, 8);
+        plan.add(0x104, 8);
+        plan.add(0x10c, 4);
+        plan.add(0x120, 4);
+        plan.add(0x200, 0);
+
+        assert_eq!(plan.blocks(), vec![(0x100, 0x118), (0x120, 0x124)]);
+    }
+
+    #[test]
+    fn merges_across_small_gaps() {
+        let mut plan = ReadPlan::with_max_gap(8);
+        plan.add(0x100, 4);
+        plan.add(0x10c, 4);
+        plan.add(0x120, 4);
+
+        assert_eq!(plan.blocks(), vec![(0x100, 0x110), (0x120, 0x124)]);
+    }
+
+    #[test]
+    fn looks_up_within_blocks() {
+        let mut plan = ReadPlan::default();
+        plan.add(0x100, 8);
+        plan.add(0x200, 4);
+        let gathered = plan.execute(read);
+
+        assert_eq!(gathered.bytes(0x102, 2), Some(&[0x02, 0x03][..]));
+        assert_eq!(
+            gathered.bytes(0x200, 4),
+            Some(&[0x00, 0x01, 0x02, 0x03][..])
+        );
+        assert_eq!(gathered.bytes(0x106, 4), None);
+        assert_eq!(gathered.bytes(0x0fe, 2), None);
+        assert_eq!(gathered.bytes(0x180, 2), None);
+    }
+
+    #[test]
+    fn leaves_out_failed_blocks() {
+        let mut plan = ReadPlan::default();
+        plan.add(0x100, 8);
+        plan.add(0x200, 4);
+        let gathered = plan.execute(|address, len| {
+            if address == 0x100 {
+                Err(Error::Timeout)
+            } else {
+                read(address, len)
+            }
+        });
+
+        assert_eq!(gathered.bytes(0x100, 4), None);
+        assert_eq!(
+            gathered.bytes(0x200, 4),
+            Some(&[0x00, 0x01, 0x02, 0x03][..])
+        );
+    }
+}
@@ -0,0 +1,558 @@
+// Decodes the game's UDP traffic: Hazel packets carrying Among Us messages.
+// Only the messages that say something about the players are decoded; the
+// rest are skipped over.
+use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+};
+
+// Hazel send options
+const UNRELIABLE: u8 = 0x00;
+const RELIABLE: u8 = 0x01;
+
+// Root message tags
+const START_GAME: u8 = 2;
+const REMOVE_PLAYER: u8 = 4;
+const GAME_DATA: u8 = 5;
+const GAME_DATA_TO: u8 = 6;
+const JOINED_GAME: u8 = 7;
+const END_GAME: u8 = 8;
+const KICK_PLAYER: u8 = 11;
+
+// GameData message tags
+const DATA: u8 = 1;
+const RPC: u8 = 2;
+const SPAWN: u8 = 4;
+const DESPAWN: u8 = 5;
+
+// Spawnable object ids
+const PLAYER_PREFAB: u32 = 4;
+
+// RPC call ids
+const COMPLETE_TASK: u8 = 1;
+const SYNC_SETTINGS: u8 = 2;
+const SET_NAME: u8 = 6;
+const SET_COLOUR: u8 = 8;
+const SET_HAT: u8 = 9;
+const SET_SKIN: u8 = 10;
+const MURDER_PLAYER: u8 = 12;
+const SEND_CHAT: u8 = 13;
+const START_MEETING: u8 = 14;
+const SET_PET: u8 = 17;
+const VOTING_COMPLETE: u8 = 23;
+// Sent to the VoteBanSystem
+const ADD_KICK_VOTE: u8 = 26;
+// Sent to GameData
+const SET_TASKS: u8 = 29;
+const USE_PLATFORM: u8 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetEvent {
+    // Our own client id and the host's
+    JoinedGame {
+        code: i32,
+        client_id: i32,
+        host_id: i32,
+    },
+    GameStarted {
+        code: i32,
+    },
+    GameEnded {
+        code: i32,
+    },
+    // Client ids, which aren't the same as player ids or net ids
+    PlayerLeft {
+        client_id: i32,
+        host_id: i32,
+        reason: DisconnectReason,
+    },
+    // Sent by the server when a kick or ban goes through
+    PlayerKicked {
+        client_id: i32,
+        banned: bool,
+    },
+    // A player's objects: the PlayerControl, PlayerPhysics and
+    // CustomNetworkTransform, in that order
+    PlayerSpawned {
+        owner_client_id: i32,
+        // From the PlayerControl's initial state
+        player_id: u8,
+        net_ids: Vec<u32>,
+    },
+    // Any object, not only a player's
+    Despawned {
+        net_id: u32,
+    },
+    // An object's serialised state, whose layout depends on its type
+    Data {
+        net_id: u32,
+        data: Vec<u8>,
+    },
+    // Sent to the object with this net id, usually a PlayerControl
+    Rpc {
+        net_id: u32,
+        call: RpcCall,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcCall {
+    // Index into the player's task list
+    CompleteTask(u32),
+    // The host's game options, sent when they change and to each joiner
+    SyncSettings { max_players: u8, language: Language },
+    SetName(String),
+    SetColour(Colour),
+    SetHat(Hat),
+    SetSkin(Skin),
+    SetPet(Pet),
+    MurderPlayer { target_net_id: u32 },
+    SendChat(String),
+    // None when the emergency button was pressed rather than a body reported
+    StartMeeting { reported: Option<u8> },
+    VotingComplete,
+    // Both client ids
+    AddKickVote { voter: i32, target: i32 },
+    // The sender is riding the Airship's moving platform across the gap
+    UsePlatform,
+    // One entry per task handed out to the player
+    SetTasks { player_id: u8, task_types: Vec<u8> },
+    Other(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    ExitGame,
+    Banned,
+    Kicked,
+    Other(u8),
+}
+
+impl From<u8> for DisconnectReason {
+    fn from(reason: u8) -> Self {
+        match reason {
+            0 => DisconnectReason::ExitGame,
+            6 => DisconnectReason::Banned,
+            7 => DisconnectReason::Kicked,
+            reason => DisconnectReason::Other(reason),
+        }
+    }
+}
+
+// The chat language a lobby is listed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    All,
+    Other,
+    Spanish,
+    Korean,
+    Russian,
+    Portuguese,
+    Arabic,
+    Filipino,
+    Polish,
+    English,
+    Unknown(u32),
+}
+
+impl From<u32> for Language {
+    fn from(keywords: u32) -> Self {
+        match keywords {
+            0 => Language::All,
+            1 => Language::Other,
+            2 => Language::Spanish,
+            4 => Language::Korean,
+            8 => Language::Russian,
+            16 => Language::Portuguese,
+            32 => Language::Arabic,
+            64 => Language::Filipino,
+            128 => Language::Polish,
+            256 => Language::English,
+            keywords => Language::Unknown(keywords),
+        }
+    }
+}
+
+// Everything recognised in one datagram's UDP payload; a truncated or
+// malformed message ends decoding but keeps what came before it
+pub fn decode_datagram(payload: &[u8]) -> Vec<NetEvent> {
+    let mut events = Vec::new();
+
+    let messages = match payload.split_first() {
+        Some((&UNRELIABLE, rest)) => rest,
+        // Followed by a two byte nonce for the ack
+        Some((&RELIABLE, rest)) if rest.len() >= 2 => &rest[2..],
+        // Hello, ping, ack and disconnect carry nothing of interest
+        _ => return events,
+    };
+
+    let mut reader = Reader(messages);
+    while let Some((tag, body)) = reader.message() {
+        if decode_root(tag, body, &mut events).is_none() {
+            tracing::trace!("malformed root message with tag {}", tag);
+        }
+    }
+
+    events
+}
+
+fn decode_root(tag: u8, body: &[u8], events: &mut Vec<NetEvent>) -> Option<()> {
+    let mut reader = Reader(body);
+
+    match tag {
+        START_GAME => events.push(NetEvent::GameStarted {
+            code: reader.i32()?,
+        }),
+        JOINED_GAME => events.push(NetEvent::JoinedGame {
+            code: reader.i32()?,
+            client_id: reader.i32()?,
+            host_id: reader.i32()?,
+        }),
+        END_GAME => events.push(NetEvent::GameEnded {
+            code: reader.i32()?,
+        }),
+        REMOVE_PLAYER => {
+            reader.i32()?;
+            events.push(NetEvent::PlayerLeft {
+                client_id: reader.i32()?,
+                host_id: reader.i32()?,
+                reason: DisconnectReason::from(reader.u8()?),
+            })
+        }
+        KICK_PLAYER => {
+            reader.i32()?;
+            events.push(NetEvent::PlayerKicked {
+                client_id: reader.packed()? as i32,
+                banned: reader.u8()? != 0,
+            })
+        }
+        GAME_DATA | GAME_DATA_TO => {
+            reader.i32()?;
+            if tag == GAME_DATA_TO {
+                // Recipient's client id
+                reader.packed()?;
+            }
+
+            while let Some((tag, body)) = reader.message() {
+                match tag {
+                    DATA => {
+                        let mut reader = Reader(body);
+                        events.push(NetEvent::Data {
+                            net_id: reader.packed()?,
+                            data: reader.0.to_vec(),
+                        });
+                    }
+                    RPC => events.push(decode_rpc(body)?),
+                    SPAWN => {
+                        if let Some(event) = decode_spawn(body)? {
+                            events.push(event);
+                        }
+                    }
+                    DESPAWN => events.push(NetEvent::Despawned {
+                        net_id: Reader(body).packed()?,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Some(())
+}
+
+fn decode_rpc(body: &[u8]) -> Option<NetEvent> {
+    let mut reader = Reader(body);
+
+    let net_id = reader.packed()?;
+    let call = match reader.u8()? {
+        COMPLETE_TASK => RpcCall::CompleteTask(reader.packed()?),
+        SYNC_SETTINGS => {
+            // Length of the serialised options, then their version
+            reader.packed()?;
+            reader.u8()?;
+            RpcCall::SyncSettings {
+                max_players: reader.u8()?,
+                language: Language::from(reader.u32()?),
+            }
+        }
+        SET_NAME => RpcCall::SetName(reader.string()?),
+        SET_COLOUR => RpcCall::SetColour(Colour::from(i32::from(reader.u8()?))),
+        SET_HAT => RpcCall::SetHat(Hat::from(reader.packed()?)),
+        SET_SKIN => RpcCall::SetSkin(Skin::from(reader.packed()?)),
+        SET_PET => RpcCall::SetPet(Pet::from(reader.packed()?)),
+        MURDER_PLAYER => RpcCall::MurderPlayer {
+            target_net_id: reader.packed()?,
+        },
+        SEND_CHAT => RpcCall::SendChat(reader.string()?),
+        START_MEETING => RpcCall::StartMeeting {
+            reported: match reader.u8()? {
+                u8::MAX => None,
+                id => Some(id),
+            },
+        },
+        VOTING_COMPLETE => RpcCall::VotingComplete,
+        ADD_KICK_VOTE => RpcCall::AddKickVote {
+            voter: reader.i32()?,
+            target: reader.i32()?,
+        },
+        USE_PLATFORM => RpcCall::UsePlatform,
+        SET_TASKS => {
+            let player_id = reader.u8()?;
+            let len = reader.packed()? as usize;
+            RpcCall::SetTasks {
+                player_id,
+                task_types: reader.take(len)?.to_vec(),
+            }
+        }
+        call => RpcCall::Other(call),
+    };
+
+    Some(NetEvent::Rpc { net_id, call })
+}
+
+// None inside when it's something other than a player being spawned
+fn decode_spawn(body: &[u8]) -> Option<Option<NetEvent>> {
+    let mut reader = Reader(body);
+
+    if reader.packed()? != PLAYER_PREFAB {
+        return Some(None);
+    }
+
+    let owner_client_id = reader.packed()? as i32;
+    // Spawn flags
+    reader.u8()?;
+
+    let count = reader.packed()?;
+    let mut net_ids = Vec::new();
+    let mut player_id = None;
+    for _ in 0..count {
+        net_ids.push(reader.packed()?);
+
+        // The component's initial state; the PlayerControl's, which comes
+        // first, is whether it's new and then the player id
+        let (_, initial) = reader.message()?;
+        if player_id.is_none() {
+            player_id = initial.get(1).copied();
+        }
+    }
+
+    Some(Some(NetEvent::PlayerSpawned {
+        owner_client_id,
+        player_id: player_id?,
+        net_ids,
+    }))
+}
+
+// Where a CustomNetworkTransform's data says the player is, in world units
+pub fn transform_position(data: &[u8]) -> Option<(f32, f32)> {
+    // Each axis is spread over the full range of a u16
+    const RANGE: (f32, f32) = (-40.0, 40.0);
+    let lerp = |value: u16| RANGE.0 + (RANGE.1 - RANGE.0) * (f32::from(value) / 65535.0);
+
+    let mut reader = Reader(data);
+    // Sequence number
+    reader.take(2)?;
+
+    Some((lerp(reader.u16()?), lerp(reader.u16()?)))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.take(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        let bytes = self.take(4)?;
+        Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Seven bits at a time, least significant first
+    fn packed(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+
+        for shift in (0..32).step_by(7) {
+            let byte = self.u8()?;
+            value |= u32::from(byte & 0x7F) << shift;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.packed()? as usize;
+        Some(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    // A nested message: two byte length, tag, then the body
+    fn message(&mut self) -> Option<(u8, &'a [u8])> {
+        let len = self.take(2)?;
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let tag = self.u8()?;
+
+        Some((tag, self.take(len)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A nested message as Reader::message reads it
+    fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = (body.len() as u16).to_le_bytes().to_vec();
+        message.push(tag);
+        message.extend_from_slice(body);
+        message
+    }
+
+    fn game_data(children: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = 0x1234_i32.to_le_bytes().to_vec();
+        for child in children {
+            body.extend_from_slice(child);
+        }
+        message(GAME_DATA, &body)
+    }
+
+    #[test]
+    fn reads_packed_integers() {
+        let mut reader = Reader(&[0x05, 0xAC, 0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x80]);
+
+        assert_eq!(reader.packed(), Some(5));
+        assert_eq!(reader.packed(), Some(300));
+        assert_eq!(reader.packed(), Some(u32::MAX));
+        // Continues past the end
+        assert_eq!(reader.packed(), None);
+
+        // Too long for a u32
+        let mut reader = Reader(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+        assert_eq!(reader.packed(), None);
+    }
+
+    #[test]
+    fn decodes_root_messages() {
+        let mut joined = Vec::new();
+        for value in &[0x1234_i32, 7, 1] {
+            joined.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut datagram = vec![RELIABLE, 0x00, 0x01];
+        datagram.extend(message(JOINED_GAME, &joined));
+        datagram.extend(message(START_GAME, &0x1234_i32.to_le_bytes()));
+
+        assert_eq!(
+            decode_datagram(&datagram),
+            vec![
+                NetEvent::JoinedGame {
+                    code: 0x1234,
+                    client_id: 7,
+                    host_id: 1,
+                },
+                NetEvent::GameStarted { code: 0x1234 },
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_game_data() {
+        let mut set_name = vec![0xAC, 0x02, SET_NAME, 3];
+        set_name.extend_from_slice(b"Red");
+
+        let mut datagram = vec![UNRELIABLE];
+        datagram.extend(game_data(&[
+            message(RPC, &set_name),
+            message(RPC, &[0x05, SET_HAT, 15]),
+            message(DESPAWN, &[0x06]),
+            message(DATA, &[0x07, 0xAA, 0xBB]),
+        ]));
+
+        assert_eq!(
+            decode_datagram(&datagram),
+            vec![
+                NetEvent::Rpc {
+                    net_id: 300,
+                    call: RpcCall::SetName("Red".to_string()),
+                },
+                NetEvent::Rpc {
+                    net_id: 5,
+                    call: RpcCall::SetHat(Hat::TopHat),
+                },
+                NetEvent::Despawned { net_id: 6 },
+                NetEvent::Data {
+                    net_id: 7,
+                    data: vec![0xAA, 0xBB],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_player_spawns() {
+        // Prefab, owner, flags, then three components
+        let mut spawn = vec![PLAYER_PREFAB as u8, 7, 0, 3];
+        spawn.push(10);
+        spawn.extend(message(1, &[1, 4]));
+        spawn.push(11);
+        spawn.extend(message(1, &[]));
+        spawn.push(12);
+        spawn.extend(message(1, &[0, 0, 0, 0]));
+
+        let mut datagram = vec![UNRELIABLE];
+        datagram.extend(game_data(&[message(SPAWN, &spawn)]));
+
+        assert_eq!(
+            decode_datagram(&datagram),
+            vec![NetEvent::PlayerSpawned {
+                owner_client_id: 7,
+                player_id: 4,
+                net_ids: vec![10, 11, 12],
+            }]
+        );
+    }
+
+    #[test]
+    fn keeps_what_came_before_a_truncated_message() {
+        let mut datagram = vec![UNRELIABLE];
+        datagram.extend(message(END_GAME, &0x1234_i32.to_le_bytes()));
+        datagram.extend(message(START_GAME, &[0x34, 0x12]));
+
+        assert_eq!(
+            decode_datagram(&datagram),
+            vec![NetEvent::GameEnded { code: 0x1234 }]
+        );
+    }
+
+    #[test]
+    fn ignores_control_packets() {
+        // Ping, and a reliable packet too short for its nonce
+        assert!(decode_datagram(&[12, 0x00, 0x01]).is_empty());
+        assert!(decode_datagram(&[RELIABLE, 0x00]).is_empty());
+        assert!(decode_datagram(&[]).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+// Talks to a helper loaded into the game process, for data that's awkward to
+// reach through offsets, like chat and timers. The helper itself lives outside
+// this crate; it listens on pipe_name(pid) and speaks the protocol below, one
+// JSON message per line, answering every request with exactly one response.
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader},
+    sync::{Mutex, MutexGuard},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{Error, RemoteError},
+    state::{State, StateSource},
+    wire::{receive, send},
+    Result,
+};
+
+// Bumped whenever a message changes shape
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub fn pipe_name(pid: usize) -> String {
+    format!(r"\\.\pipe\taskinator-{}", pid)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HelperRequest {
+    // Sent first; the helper replies with its own version
+    Hello { version: u32 },
+    State,
+    // Messages with a sequence number above this
+    Chat { after: u64 },
+    Timers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HelperResponse {
+    Hello { version: u32 },
+    State(State),
+    Chat(Vec<ChatMessage>),
+    Timers(Timers),
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    // Increases by one per message for as long as the helper is loaded
+    pub seq: u64,
+    pub player_id: u8,
+    pub text: String,
+}
+
+// Seconds remaining; None when the timer isn't running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timers {
+    pub meeting: Option<f32>,
+    pub kill_cooldown: Option<f32>,
+    pub emergency_cooldown: Option<f32>,
+}
+
+pub struct HelperGame {
+    pid: usize,
+    connection: Mutex<Connection>,
+}
+
+struct Connection {
+    reader: BufReader<File>,
+    writer: File,
+}
+
+impl HelperGame {
+    pub fn connect(pid: usize) -> Result<Self> {
+        let pipe = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe_name(pid))
+            .map_err(RemoteError::Connection)?;
+
+        let game = HelperGame {
+            pid,
+            connection: Mutex::new(Connection {
+                writer: pipe.try_clone().map_err(RemoteError::Connection)?,
+                reader: BufReader::new(pipe),
+            }),
+        };
+
+        match game.request(&HelperRequest::Hello {
+            version: PROTOCOL_VERSION,
+        })? {
+            HelperResponse::Hello { version } if version == PROTOCOL_VERSION => Ok(game),
+            HelperResponse::Hello { version } => Err(RemoteError::Protocol(format!(
+                "helper speaks version {}, expected {}",
+                version, PROTOCOL_VERSION
+            ))
+            .into()),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid
+    }
+
+    pub fn state(&self) -> Result<State> {
+        match self.request(&HelperRequest::State)? {
+            HelperResponse::State(state) => Ok(state),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    pub fn chat_since(&self, after: u64) -> Result<Vec<ChatMessage>> {
+        match self.request(&HelperRequest::Chat { after })? {
+            HelperResponse::Chat(messages) => Ok(messages),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    pub fn timers(&self) -> Result<Timers> {
+        match self.request(&HelperRequest::Timers)? {
+            HelperResponse::Timers(timers) => Ok(timers),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    fn request(&self, request: &HelperRequest) -> Result<HelperResponse> {
+        let mut connection = self.lock();
+
+        send(&mut connection.writer, request).map_err(RemoteError::Connection)?;
+
+        match receive(&mut connection.reader).map_err(RemoteError::Connection)? {
+            Some(HelperResponse::Error { message }) => Err(RemoteError::Agent {
+                message,
+                transient: true,
+                fatal: false,
+            }
+            .into()),
+            Some(response) => Ok(response),
+            None => Err(RemoteError::Connection(io::ErrorKind::UnexpectedEof.into()).into()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        // Requests are written and answered whole, so a panic between them
+        // leaves the pipe usable
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn unexpected(response: HelperResponse) -> Error {
+    RemoteError::Protocol(format!("{:?}", response)).into()
+}
+
+impl StateSource for HelperGame {
+    fn state(&self) -> Result<State> {
+        HelperGame::state(self)
+    }
+}
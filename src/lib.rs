@@ -6,7 +6,76 @@
     warnings
 )]
 
+// Portable core: the state types, diffing and serialisation
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod colour;
+pub mod cosmetics;
 pub mod error;
+pub mod events;
+pub mod fake;
+pub mod faults;
+#[cfg(any(feature = "capture", test))]
+pub mod hazel;
+pub mod metrics;
+#[cfg(feature = "offset-updates")]
+pub mod offset_updates;
+pub mod offsets;
+pub mod packs;
+#[cfg(feature = "parquet-export")]
+pub mod parquet_export;
+pub mod prelude;
+#[cfg(feature = "push")]
+pub mod push;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod redact;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod sanitize;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod state;
+pub mod view;
+pub mod voice;
+#[cfg(any(feature = "remote", feature = "helper"))]
+mod wire;
+
+// Reading a live game, which needs Win32
+#[cfg(windows)]
+pub mod broker;
+#[cfg(windows)]
+pub mod builder;
+#[cfg(windows)]
+pub mod exit;
+#[cfg(all(windows, feature = "ffi"))]
+pub mod ffi;
+#[cfg(windows)]
 pub mod game;
+#[cfg(any(windows, test))]
+mod gather;
+#[cfg(all(windows, feature = "helper"))]
+pub mod helper;
+#[cfg(all(windows, feature = "node"))]
+pub mod node;
+#[cfg(windows)]
+pub mod privileges;
+#[cfg(windows)]
+pub mod process;
+#[cfg(all(windows, feature = "prometheus"))]
+pub mod prometheus;
+#[cfg(all(windows, feature = "python"))]
+pub mod python;
+#[cfg(windows)]
+pub mod retry;
+#[cfg(all(windows, feature = "service"))]
+pub mod service;
+#[cfg(windows)]
+pub mod stats;
+#[cfg(windows)]
+pub mod watcher;
+#[cfg(windows)]
+pub mod window;
 
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+pub type Result<T> = std::result::Result<T, error::Error>;
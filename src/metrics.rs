@@ -0,0 +1,52 @@
+// Figures derived from a single state that overlays and bots tend to show,
+// worked out once here rather than in every one of them. Sabotage timers and
+// votes aren't part of the state, so neither are the figures that would need
+// them.
+use crate::state::State;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metrics {
+    // From 0 to 1; None outside a game or before tasks are handed out
+    pub task_progress: Option<f32>,
+    // Players who left are counted as neither
+    pub alive_crew: usize,
+    pub alive_impostors: usize,
+    // Crewmates that have to die, by kill or ejection, before the impostors
+    // win; None outside a game
+    pub kills_to_impostor_win: Option<usize>,
+}
+
+pub fn metrics(state: &State) -> Metrics {
+    let alive = |impostor: bool| {
+        state
+            .players()
+            .iter()
+            .filter(|p| !p.dead && !p.disconnected && p.impostor == impostor)
+            .count()
+    };
+    let (alive_crew, alive_impostors) = (alive(false), alive(true));
+
+    match state {
+        State::InGame {
+            tasks_completed,
+            tasks_total,
+            ..
+        } => Metrics {
+            task_progress: if *tasks_total == 0 {
+                None
+            } else {
+                Some(*tasks_completed as f32 / *tasks_total as f32)
+            },
+            alive_crew,
+            alive_impostors,
+            kills_to_impostor_win: Some(alive_crew.saturating_sub(alive_impostors)),
+        },
+        State::Menu | State::Lobby { .. } | State::Ended { .. } => Metrics {
+            task_progress: None,
+            alive_crew,
+            alive_impostors,
+            kills_to_impostor_win: None,
+        },
+    }
+}
@@ -0,0 +1,117 @@
+// Node.js addon for Electron overlays; states and events cross over as plain
+// objects, shaped the same as the serde representation
+use std::{thread, time::Duration};
+
+use napi::{
+    threadsafe_function::{
+        ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+    },
+    JsFunction,
+};
+use napi_derive::napi;
+
+use crate::{
+    error::Error,
+    game::Game,
+    watcher::{GameWatcher, StopHandle},
+};
+
+const DEFAULT_INTERVAL_MS: u32 = 100;
+
+fn to_js_err(error: impl ToString) -> napi::Error {
+    napi::Error::from_reason(error.to_string())
+}
+
+fn attach(pid: Option<u32>) -> Result<Game, Error> {
+    match pid {
+        Some(pid) => Game::from_pid(pid as usize),
+        None => Game::find(),
+    }
+}
+
+#[napi(js_name = "Game")]
+pub struct JsGame {
+    game: Game,
+}
+
+#[napi]
+impl JsGame {
+    // Attaches to the given process, or the first running game without one
+    #[napi(factory)]
+    pub fn attach(pid: Option<u32>) -> napi::Result<Self> {
+        let game = attach(pid).map_err(to_js_err)?;
+        Ok(JsGame { game })
+    }
+
+    #[napi(getter)]
+    pub fn pid(&self) -> u32 {
+        self.game.pid() as u32
+    }
+
+    #[napi]
+    pub fn is_running(&self) -> bool {
+        self.game.is_running()
+    }
+
+    #[napi]
+    pub fn poll(&self) -> napi::Result<serde_json::Value> {
+        let state = self.game.state().map_err(to_js_err)?;
+        serde_json::to_value(&state).map_err(to_js_err)
+    }
+}
+
+#[napi(object)]
+pub struct SubscribeOptions {
+    pub pid: Option<u32>,
+    pub interval_ms: Option<u32>,
+}
+
+#[napi]
+pub struct Subscription {
+    stop: StopHandle,
+}
+
+#[napi]
+impl Subscription {
+    #[napi]
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+}
+
+// Calls back with each event from a watcher thread attached to a game of its
+// own, until the subscription is stopped or the watcher gives up
+#[napi(ts_args_type = "callback: (event: object) => void, options?: SubscribeOptions")]
+pub fn subscribe(
+    callback: JsFunction,
+    options: Option<SubscribeOptions>,
+) -> napi::Result<Subscription> {
+    let callback: ThreadsafeFunction<serde_json::Value, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<serde_json::Value>| {
+            Ok(vec![ctx.value])
+        })?;
+
+    let (pid, interval_ms) = match options {
+        Some(options) => (options.pid, options.interval_ms),
+        None => (None, None),
+    };
+    let interval = Duration::from_millis(interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).into());
+
+    let watcher = GameWatcher::new(attach(pid).map_err(to_js_err)?);
+    let stop = watcher.stop_handle();
+    let (_, receiver) = watcher.spawn_thread(interval);
+
+    // Ends once the watcher thread drops its sender, releasing the callback
+    thread::spawn(move || {
+        for event in receiver {
+            match serde_json::to_value(&event) {
+                Ok(value) => {
+                    callback.call(value, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                Err(e) => tracing::debug!("couldn't convert {:?}: {}", event, e),
+            }
+        }
+    });
+
+    Ok(Subscription { stop })
+}
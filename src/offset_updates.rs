@@ -0,0 +1,187 @@
+// Fetches offsets published after a game update, so apps built on this crate
+// can follow a new patch without a new release of their own. Nothing is
+// fetched unless the app calls fetch, and the document has to be signed by a
+// key the app supplies.
+//
+// The document is {"payload": "...", "signature": "..."}, where the payload is
+// an OffsetTable as a JSON string and the signature is the hex encoded Ed25519
+// signature of the payload's bytes. Fields missing from the payload keep the
+// values from the local table, so an app's own overrides survive an update
+// that doesn't touch them.
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io,
+    sync::Arc,
+    time::Duration,
+};
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::Deserialize;
+
+use crate::offsets::OffsetTable;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UpdateError {
+    Tls(native_tls::Error),
+    Fetch(Box<ureq::Error>),
+    Io(io::Error),
+    Json(serde_json::Error),
+    InvalidKey,
+    InvalidSignature,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    payload: String,
+    signature: String,
+}
+
+pub fn fetch(
+    url: &str,
+    public_key: &[u8; 32],
+    local: &OffsetTable,
+) -> Result<OffsetTable, UpdateError> {
+    let agent = ureq::AgentBuilder::new()
+        .tls_connector(Arc::new(
+            native_tls::TlsConnector::new().map_err(UpdateError::Tls)?,
+        ))
+        .timeout(FETCH_TIMEOUT)
+        .build();
+
+    let document = agent
+        .get(url)
+        .call()
+        .map_err(|e| UpdateError::Fetch(Box::new(e)))?
+        .into_string()
+        .map_err(UpdateError::Io)?;
+
+    verify(&document, public_key, local)
+}
+
+// For documents obtained some other way, e.g. bundled or cached on disk
+pub fn verify(
+    document: &str,
+    public_key: &[u8; 32],
+    local: &OffsetTable,
+) -> Result<OffsetTable, UpdateError> {
+    let document: Document = serde_json::from_str(document).map_err(UpdateError::Json)?;
+
+    let key = VerifyingKey::from_bytes(public_key).map_err(|_| UpdateError::InvalidKey)?;
+    let signature = decode_hex(&document.signature)
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or(UpdateError::InvalidSignature)?;
+
+    key.verify_strict(document.payload.as_bytes(), &signature)
+        .map_err(|_| UpdateError::InvalidSignature)?;
+
+    let mut merged = serde_json::to_value(local).map_err(UpdateError::Json)?;
+    let payload: serde_json::Value =
+        serde_json::from_str(&document.payload).map_err(UpdateError::Json)?;
+
+    if let (Some(merged), Some(payload)) = (merged.as_object_mut(), payload.as_object()) {
+        for (field, value) in payload {
+            merged.insert(field.clone(), value.clone());
+        }
+    }
+
+    serde_json::from_value(merged).map_err(UpdateError::Json)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            UpdateError::Tls(e) => f.write_fmt(format_args!("couldn't set up TLS: {}", e)),
+            UpdateError::Fetch(e) => f.write_fmt(format_args!("couldn't fetch offsets: {}", e)),
+            UpdateError::Io(e) => f.write_fmt(format_args!("couldn't read offsets: {}", e)),
+            UpdateError::Json(e) => f.write_fmt(format_args!("malformed offsets document: {}", e)),
+            UpdateError::InvalidKey => f.write_str("the public key isn't a valid Ed25519 key"),
+            UpdateError::InvalidSignature => {
+                f.write_str("the offsets document isn't signed by the expected key")
+            }
+        }
+    }
+}
+
+impl StdError for UpdateError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            UpdateError::Tls(e) => Some(e),
+            UpdateError::Fetch(e) => Some(e),
+            UpdateError::Io(e) => Some(e),
+            UpdateError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    fn key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn document(payload: &str, key: &SigningKey) -> String {
+        let signature: String = key
+            .sign(payload.as_bytes())
+            .to_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        serde_json::json!({ "payload": payload, "signature": signature }).to_string()
+    }
+
+    #[test]
+    fn merges_a_signed_payload() {
+        let local = OffsetTable::default();
+        let document = document(r#"{"statics": 4096}"#, &key());
+
+        let merged = verify(&document, key().verifying_key().as_bytes(), &local).unwrap();
+        assert_eq!(merged.statics, 4096);
+        assert_eq!(merged.client_state_class, local.client_state_class);
+    }
+
+    #[test]
+    fn rejects_other_signatures() {
+        let local = OffsetTable::default();
+        let public_key = key().verifying_key().to_bytes();
+
+        let forged = document(r#"{"statics": 4096}"#, &SigningKey::from_bytes(&[8; 32]));
+        assert!(matches!(
+            verify(&forged, &public_key, &local),
+            Err(UpdateError::InvalidSignature)
+        ));
+
+        let tampered = document(r#"{"statics": 4096}"#, &key()).replace("4096", "4097");
+        assert!(matches!(
+            verify(&tampered, &public_key, &local),
+            Err(UpdateError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex("00ff7A"), Some(vec![0x00, 0xFF, 0x7A]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+}
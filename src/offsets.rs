@@ -0,0 +1,79 @@
+// Where the reader finds things in GameAssembly.dll and the objects it points
+// to; these move with every game update
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OffsetTable {
+    // Class pointers, relative to GameAssembly.dll
+    pub client_state_class: u32,
+    pub player_manager_class: u32,
+    pub meeting_screen_class: u32,
+    // Within an il2cpp class
+    pub statics: u32,
+    // Within AmongUsClient
+    pub internal_state: u32,
+    // Within GameData; followed by the task totals
+    pub player_list: u32,
+    // Within MeetingHud
+    pub meeting_state: u32,
+}
+
+const V2021_06: OffsetTable = OffsetTable {
+    client_state_class: 0x01BAA960,
+    player_manager_class: 0x01BC2EAC,
+    meeting_screen_class: 0x01B9F7A0,
+    statics: 0x5C,
+    internal_state: 0x70,
+    player_list: 0x24,
+    meeting_state: 0x74,
+};
+
+// Oldest first; each table needs its offsets-<version> feature, and
+// offsets-latest turns on the newest
+const BUILTIN: &[(&str, OffsetTable)] = &[
+    #[cfg(feature = "offsets-2021-06")]
+    ("2021-06", V2021_06),
+];
+
+// Experimental tables for Steam beta branches, keyed by branch name; they may
+// be wrong or go stale as the beta moves, so they need offsets-beta
+const BETA: &[(&str, OffsetTable)] = &[];
+
+impl OffsetTable {
+    // A bundled table by version, or "latest" for the newest one enabled
+    pub fn builtin(version: &str) -> Option<OffsetTable> {
+        if version == "latest" {
+            return BUILTIN.last().map(|(_, table)| table.clone());
+        }
+
+        BUILTIN
+            .iter()
+            .find(|(builtin, _)| *builtin == version)
+            .map(|(_, table)| table.clone())
+    }
+
+    pub fn builtin_versions() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(version, _)| *version)
+    }
+
+    pub fn beta(branch: &str) -> Option<OffsetTable> {
+        if !cfg!(feature = "offsets-beta") {
+            return None;
+        }
+
+        BETA.iter()
+            .find(|(beta, _)| *beta == branch)
+            .map(|(_, table)| table.clone())
+    }
+}
+
+// A reader with no table to fall back on can't attach to anything
+#[cfg(not(feature = "offsets-2021-06"))]
+compile_error!("enable at least one offsets-<version> feature, e.g. offsets-latest");
+
+// The newest version among the enabled offsets-<version> features; use builtin
+// to stay on a particular version across releases
+impl Default for OffsetTable {
+    fn default() -> Self {
+        BUILTIN[BUILTIN.len() - 1].1.clone()
+    }
+}
@@ -0,0 +1,44 @@
+// Offset packs describe extra per-player fields added by mods such as Town of
+// Us or The Other Roles, so their roles can be read from modded lobbies. The
+// crate doesn't ship any; the values depend on the mod's build.
+
+#[derive(Debug, Clone)]
+pub struct OffsetPack {
+    pub name: String,
+    pub fields: Vec<RoleField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleField {
+    // Key the value is stored under in Player::extra_roles
+    pub key: String,
+    pub base: FieldBase,
+    pub offset: u32,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldBase {
+    // The GameData.PlayerInfo object the vanilla fields are read from
+    PlayerInfo,
+    // The PlayerControl object PlayerInfo points at
+    PlayerControl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Bool,
+    U8,
+    I32,
+    U32,
+    // Pointer to an il2cpp string
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoleValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
@@ -0,0 +1,344 @@
+// Writes a recording out as Parquet tables, for loading a match straight into
+// pandas or Polars: frames.parquet has a row per state, players.parquet a row
+// per player per state, and events.parquet the changes between states. Rows
+// share elapsed_ms with the recording. Positions aren't recorded, so there's no
+// table for them.
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::File,
+    io,
+    path::Path,
+    sync::Arc,
+};
+
+use arrow_array::{
+    builder::{
+        BooleanBuilder, Int32Builder, StringBuilder, UInt32Builder, UInt64Builder, UInt8Builder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use parquet::{
+    arrow::ArrowWriter, basic::Compression, errors::ParquetError,
+    file::properties::WriterProperties,
+};
+
+use crate::{
+    events::{self, Event},
+    recording::Frame,
+    state::State,
+};
+
+// Rows buffered per table before they're written as a row group
+const BATCH_ROWS: usize = 65536;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExportError {
+    Io(io::Error),
+    Arrow(ArrowError),
+    Parquet(ParquetError),
+}
+
+// Each of the three tables is written to dir, replacing any already there
+pub fn export(
+    frames: impl IntoIterator<Item = io::Result<Frame>>,
+    dir: impl AsRef<Path>,
+) -> Result<(), ExportError> {
+    let dir = dir.as_ref();
+    let mut frame_table = Table::create(&dir.join("frames.parquet"), FrameRows::schema())?;
+    let mut player_table = Table::create(&dir.join("players.parquet"), PlayerRows::schema())?;
+    let mut event_table = Table::create(&dir.join("events.parquet"), EventRows::schema())?;
+
+    let mut frame_rows = FrameRows::default();
+    let mut player_rows = PlayerRows::default();
+    let mut event_rows = EventRows::default();
+    let mut last: Option<State> = None;
+
+    for frame in frames {
+        let frame = frame.map_err(ExportError::Io)?;
+        let elapsed_ms = frame.elapsed.as_millis() as u64;
+
+        frame_rows.push(elapsed_ms, &frame.state);
+        for player in frame.state.players() {
+            player_rows.push(elapsed_ms, player);
+        }
+        if let Some(last) = &last {
+            for event in events::diff(last, &frame.state) {
+                event_rows.push(elapsed_ms, &event);
+            }
+        }
+
+        frame_table.fill(frame_rows.len, || frame_rows.finish(), false)?;
+        player_table.fill(player_rows.len, || player_rows.finish(), false)?;
+        event_table.fill(event_rows.len, || event_rows.finish(), false)?;
+
+        last = Some(frame.state);
+    }
+
+    frame_table.fill(frame_rows.len, || frame_rows.finish(), true)?;
+    player_table.fill(player_rows.len, || player_rows.finish(), true)?;
+    event_table.fill(event_rows.len, || event_rows.finish(), true)?;
+
+    frame_table.close()?;
+    player_table.close()?;
+    event_table.close()
+}
+
+struct Table {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+}
+
+impl Table {
+    fn create(path: &Path, schema: SchemaRef) -> Result<Self, ExportError> {
+        let file = File::create(path).map_err(ExportError::Io)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        Ok(Table {
+            writer: ArrowWriter::try_new(file, schema.clone(), Some(properties))
+                .map_err(ExportError::Parquet)?,
+            schema,
+        })
+    }
+
+    // Writes out the buffered rows once there are enough, or whatever's left
+    // at the end
+    fn fill(
+        &mut self,
+        len: usize,
+        finish: impl FnOnce() -> Vec<ArrayRef>,
+        last: bool,
+    ) -> Result<(), ExportError> {
+        if len == 0 || (len < BATCH_ROWS && !last) {
+            return Ok(());
+        }
+
+        let batch =
+            RecordBatch::try_new(self.schema.clone(), finish()).map_err(ExportError::Arrow)?;
+        self.writer.write(&batch).map_err(ExportError::Parquet)
+    }
+
+    fn close(self) -> Result<(), ExportError> {
+        self.writer.close().map_err(ExportError::Parquet)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct FrameRows {
+    len: usize,
+    elapsed_ms: UInt64Builder,
+    phase: StringBuilder,
+    meeting: StringBuilder,
+    tasks_completed: UInt32Builder,
+    tasks_total: UInt32Builder,
+}
+
+impl FrameRows {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("elapsed_ms", DataType::UInt64, false),
+            Field::new("phase", DataType::Utf8, false),
+            Field::new("meeting", DataType::Utf8, true),
+            Field::new("tasks_completed", DataType::UInt32, true),
+            Field::new("tasks_total", DataType::UInt32, true),
+        ]))
+    }
+
+    fn push(&mut self, elapsed_ms: u64, state: &State) {
+        self.len += 1;
+        self.elapsed_ms.append_value(elapsed_ms);
+
+        match state {
+            State::InGame {
+                meeting,
+                tasks_completed,
+                tasks_total,
+                ..
+            } => {
+                self.phase.append_value("in_game");
+                self.meeting.append_value(meeting.to_string());
+                self.tasks_completed.append_value(*tasks_completed);
+                self.tasks_total.append_value(*tasks_total);
+            }
+            State::Menu | State::Lobby { .. } | State::Ended { .. } => {
+                self.phase.append_value(match state {
+                    State::Menu => "menu",
+                    State::Ended { .. } => "ended",
+                    _ => "lobby",
+                });
+                self.meeting.append_null();
+                self.tasks_completed.append_null();
+                self.tasks_total.append_null();
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        self.len = 0;
+        vec![
+            Arc::new(self.elapsed_ms.finish()),
+            Arc::new(self.phase.finish()),
+            Arc::new(self.meeting.finish()),
+            Arc::new(self.tasks_completed.finish()),
+            Arc::new(self.tasks_total.finish()),
+        ]
+    }
+}
+
+#[derive(Default)]
+struct PlayerRows {
+    len: usize,
+    elapsed_ms: UInt64Builder,
+    player_id: UInt8Builder,
+    name: StringBuilder,
+    colour: Int32Builder,
+    hat: UInt32Builder,
+    pet: UInt32Builder,
+    skin: UInt32Builder,
+    impostor: BooleanBuilder,
+    dead: BooleanBuilder,
+    disconnected: BooleanBuilder,
+}
+
+impl PlayerRows {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("elapsed_ms", DataType::UInt64, false),
+            Field::new("player_id", DataType::UInt8, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("colour", DataType::Int32, false),
+            Field::new("hat", DataType::UInt32, false),
+            Field::new("pet", DataType::UInt32, false),
+            Field::new("skin", DataType::UInt32, false),
+            Field::new("impostor", DataType::Boolean, false),
+            Field::new("dead", DataType::Boolean, false),
+            Field::new("disconnected", DataType::Boolean, false),
+        ]))
+    }
+
+    fn push(&mut self, elapsed_ms: u64, player: &crate::state::Player) {
+        self.len += 1;
+        self.elapsed_ms.append_value(elapsed_ms);
+        self.player_id.append_value(player.id);
+        self.name.append_value(&player.name);
+        self.colour.append_value(player.colour.id());
+        self.hat.append_value(player.hat.id());
+        self.pet.append_value(player.pet.id());
+        self.skin.append_value(player.skin.id());
+        self.impostor.append_value(player.impostor);
+        self.dead.append_value(player.dead);
+        self.disconnected.append_value(player.disconnected);
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        self.len = 0;
+        vec![
+            Arc::new(self.elapsed_ms.finish()),
+            Arc::new(self.player_id.finish()),
+            Arc::new(self.name.finish()),
+            Arc::new(self.colour.finish()),
+            Arc::new(self.hat.finish()),
+            Arc::new(self.pet.finish()),
+            Arc::new(self.skin.finish()),
+            Arc::new(self.impostor.finish()),
+            Arc::new(self.dead.finish()),
+            Arc::new(self.disconnected.finish()),
+        ]
+    }
+}
+
+#[derive(Default)]
+struct EventRows {
+    len: usize,
+    elapsed_ms: UInt64Builder,
+    kind: StringBuilder,
+    player_id: UInt8Builder,
+    // Names as text, everything else as its id
+    old: StringBuilder,
+    new: StringBuilder,
+}
+
+impl EventRows {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("elapsed_ms", DataType::UInt64, false),
+            Field::new("kind", DataType::Utf8, false),
+            Field::new("player_id", DataType::UInt8, false),
+            Field::new("old", DataType::Utf8, false),
+            Field::new("new", DataType::Utf8, false),
+        ]))
+    }
+
+    fn push(&mut self, elapsed_ms: u64, event: &Event) {
+        let (kind, id, old, new) = match event {
+            Event::NameChanged { id, old, new } => ("name_changed", id, old.clone(), new.clone()),
+            Event::ColourChanged { id, old, new } => (
+                "colour_changed",
+                id,
+                old.id().to_string(),
+                new.id().to_string(),
+            ),
+            Event::HatChanged { id, old, new } => (
+                "hat_changed",
+                id,
+                old.id().to_string(),
+                new.id().to_string(),
+            ),
+            Event::PetChanged { id, old, new } => (
+                "pet_changed",
+                id,
+                old.id().to_string(),
+                new.id().to_string(),
+            ),
+            Event::SkinChanged { id, old, new } => (
+                "skin_changed",
+                id,
+                old.id().to_string(),
+                new.id().to_string(),
+            ),
+        };
+
+        self.len += 1;
+        self.elapsed_ms.append_value(elapsed_ms);
+        self.kind.append_value(kind);
+        self.player_id.append_value(*id);
+        self.old.append_value(old);
+        self.new.append_value(new);
+    }
+
+    fn finish(&mut self) -> Vec<ArrayRef> {
+        self.len = 0;
+        vec![
+            Arc::new(self.elapsed_ms.finish()),
+            Arc::new(self.kind.finish()),
+            Arc::new(self.player_id.finish()),
+            Arc::new(self.old.finish()),
+            Arc::new(self.new.finish()),
+        ]
+    }
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            ExportError::Io(e) => f.write_fmt(format_args!("couldn't read or write: {}", e)),
+            ExportError::Arrow(e) => f.write_fmt(format_args!("couldn't build a table: {}", e)),
+            ExportError::Parquet(e) => f.write_fmt(format_args!("couldn't write Parquet: {}", e)),
+        }
+    }
+}
+
+impl StdError for ExportError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ExportError::Io(e) => Some(e),
+            ExportError::Arrow(e) => Some(e),
+            ExportError::Parquet(e) => Some(e),
+        }
+    }
+}
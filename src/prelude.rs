@@ -0,0 +1,20 @@
+pub use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    error::Error,
+    events::Event,
+    offsets::OffsetTable,
+    redact::{LivingView, Redaction},
+    state::{MeetingState, Phase, Player, State, StateSource},
+    view::{PlayerView, View},
+    voice::{MutePolicy, Voice},
+    Result,
+};
+
+#[cfg(windows)]
+pub use crate::{
+    builder::GameBuilder,
+    game::{AccessMode, Consistency, Distribution, Game, ModuleMatch, PlayerRef, StateReadOptions},
+    retry::RetryPolicy,
+    watcher::{GameWatcher, Overflow, StopHandle},
+};
@@ -0,0 +1,123 @@
+use std::{
+    ffi::{c_void, OsStr},
+    iter::once,
+    mem::{size_of, MaybeUninit},
+    os::windows::ffi::OsStrExt,
+    ptr::{null, null_mut},
+};
+
+use winapi::{
+    shared::ntdef::{HANDLE, LUID},
+    um::{
+        errhandlingapi::GetLastError,
+        handleapi::CloseHandle,
+        processthreadsapi::{GetCurrentProcess, OpenProcessToken},
+        securitybaseapi::GetTokenInformation,
+        winbase::LookupPrivilegeValueW,
+        winnt::{
+            TokenElevation, TokenPrivileges, LUID_AND_ATTRIBUTES, SE_DEBUG_NAME,
+            SE_PRIVILEGE_ENABLED, TOKEN_ELEVATION, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        },
+    },
+};
+
+use crate::{error::AttachError, Result};
+
+#[derive(Debug, Clone)]
+pub struct Privileges {
+    // Running as administrator with a full (non-filtered) token
+    pub elevated: bool,
+    // SeDebugPrivilege is present in the token, and whether it's enabled
+    pub debug_privilege: bool,
+    pub debug_privilege_enabled: bool,
+}
+
+impl Privileges {
+    // Elevation is only needed when the game itself is running elevated
+    pub fn advice(&self) -> &'static str {
+        if self.elevated {
+            "this process is elevated; access should only be denied by security software"
+        } else {
+            "if Among Us is running as administrator, run this program as administrator too"
+        }
+    }
+}
+
+pub fn check() -> Result<Privileges> {
+    let mut token: HANDLE = null_mut();
+
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return Err(AttachError::PrivilegeQuery(unsafe { GetLastError() }).into());
+    }
+
+    let privileges = unsafe { query_token(token) };
+    unsafe { CloseHandle(token) };
+
+    privileges
+}
+
+unsafe fn query_token(token: HANDLE) -> Result<Privileges> {
+    let mut elevation = MaybeUninit::<TOKEN_ELEVATION>::uninit();
+    let mut len = 0;
+
+    if GetTokenInformation(
+        token,
+        TokenElevation,
+        elevation.as_mut_ptr() as *mut c_void,
+        size_of::<TOKEN_ELEVATION>() as u32,
+        &mut len,
+    ) == 0
+    {
+        return Err(AttachError::PrivilegeQuery(GetLastError()).into());
+    }
+
+    let elevated = elevation.assume_init().TokenIsElevated != 0;
+
+    // First call only reports the size of the privilege list
+    GetTokenInformation(token, TokenPrivileges, null_mut(), 0, &mut len);
+
+    // u32s keep the buffer aligned for TOKEN_PRIVILEGES
+    let mut buffer: Vec<u32> = vec![0; (len as usize).div_ceil(size_of::<u32>())];
+    if GetTokenInformation(
+        token,
+        TokenPrivileges,
+        buffer.as_mut_ptr() as *mut c_void,
+        len,
+        &mut len,
+    ) == 0
+    {
+        return Err(AttachError::PrivilegeQuery(GetLastError()).into());
+    }
+
+    let debug_luid = lookup_privilege(SE_DEBUG_NAME)?;
+
+    let token_privileges = buffer.as_ptr() as *const TOKEN_PRIVILEGES;
+    let privileges: &[LUID_AND_ATTRIBUTES] = std::slice::from_raw_parts(
+        (*token_privileges).Privileges.as_ptr(),
+        (*token_privileges).PrivilegeCount as usize,
+    );
+
+    let debug = privileges.iter().find(|privilege| {
+        privilege.Luid.LowPart == debug_luid.LowPart
+            && privilege.Luid.HighPart == debug_luid.HighPart
+    });
+
+    Ok(Privileges {
+        elevated,
+        debug_privilege: debug.is_some(),
+        debug_privilege_enabled: debug
+            .map(|privilege| privilege.Attributes & SE_PRIVILEGE_ENABLED != 0)
+            .unwrap_or(false),
+    })
+}
+
+unsafe fn lookup_privilege(name: &str) -> Result<LUID> {
+    let name: Vec<u16> = OsStr::new(name).encode_wide().chain(once(0)).collect();
+    let mut luid = MaybeUninit::<LUID>::uninit();
+
+    if LookupPrivilegeValueW(null(), name.as_ptr(), luid.as_mut_ptr()) == 0 {
+        return Err(AttachError::PrivilegeQuery(GetLastError()).into());
+    }
+
+    Ok(luid.assume_init())
+}
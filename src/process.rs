@@ -0,0 +1,380 @@
+use std::{
+    ffi::OsString,
+    mem::{size_of, zeroed},
+    os::windows::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use winapi::{
+    shared::ntdef::HANDLE,
+    um::{
+        errhandlingapi::GetLastError,
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        processthreadsapi::{OpenProcess, OpenProcessToken},
+        securitybaseapi::GetTokenInformation,
+        tlhelp32::{
+            CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, Process32FirstW,
+            Process32NextW, MODULEENTRY32W, PROCESSENTRY32W, TH32CS_SNAPMODULE,
+            TH32CS_SNAPMODULE32, TH32CS_SNAPPROCESS,
+        },
+        winbase::{LookupAccountSidW, QueryFullProcessImageNameW},
+        winnt::{TokenUser, PROCESS_QUERY_LIMITED_INFORMATION, TOKEN_QUERY, TOKEN_USER},
+    },
+};
+
+use crate::{error::AttachError, game::Distribution, watcher::StopHandle, window::Window, Result};
+
+pub const GAME_EXECUTABLE: &str = "Among Us.exe";
+
+// Pids of every running Among Us process, in enumeration order
+pub fn find_game_pids() -> Result<Vec<usize>> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(AttachError::EnumProcesses(unsafe { GetLastError() }).into());
+    }
+
+    let mut entry: PROCESSENTRY32W = unsafe { zeroed() };
+    entry.dwSize = size_of::<PROCESSENTRY32W>() as u32;
+
+    let mut pids = Vec::new();
+    let mut more = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+
+    while more {
+        if String::from_utf16_lossy(until_nul(&entry.szExeFile))
+            .eq_ignore_ascii_case(GAME_EXECUTABLE)
+        {
+            pids.push(entry.th32ProcessID as usize);
+        }
+
+        more = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+    }
+
+    unsafe { CloseHandle(snapshot) };
+
+    Ok(pids)
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub base: usize,
+    pub size: usize,
+}
+
+// Both 32- and 64-bit modules of the process
+pub fn modules(pid: usize) -> Result<Vec<Module>> {
+    let snapshot =
+        unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid as u32) };
+
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(AttachError::EnumModules(unsafe { GetLastError() }).into());
+    }
+
+    let mut entry: MODULEENTRY32W = unsafe { zeroed() };
+    entry.dwSize = size_of::<MODULEENTRY32W>() as u32;
+
+    let mut modules = Vec::new();
+    let mut more = unsafe { Module32FirstW(snapshot, &mut entry) } != 0;
+
+    while more {
+        modules.push(Module {
+            name: String::from_utf16_lossy(until_nul(&entry.szModule)),
+            path: OsString::from_wide(until_nul(&entry.szExePath)).into(),
+            base: entry.modBaseAddr as usize,
+            size: entry.modBaseSize as usize,
+        });
+
+        more = unsafe { Module32NextW(snapshot, &mut entry) } != 0;
+    }
+
+    unsafe { CloseHandle(snapshot) };
+
+    Ok(modules)
+}
+
+// Modules belonging to BepInEx, MelonLoader or the doorstop proxies they're
+// injected through
+pub(crate) fn find_mod_indicators(pid: usize, image_path: &Path) -> Vec<PathBuf> {
+    const LOADER_DIRS: [&str; 2] = ["bepinex", "melonloader"];
+    const LOADER_MODULES: [&str; 3] = ["doorstop.dll", "mono-2.0-bdwgc.dll", "coreclr.dll"];
+    const PROXY_MODULES: [&str; 2] = ["winhttp.dll", "version.dll"];
+
+    let modules = match modules(pid) {
+        Ok(modules) => modules,
+        Err(e) => {
+            tracing::debug!("unable to check for mods: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let game_dir = image_path.parent();
+
+    modules
+        .into_iter()
+        .filter(|module| {
+            let name = module.name.to_ascii_lowercase();
+            let path = module.path.to_string_lossy().to_ascii_lowercase();
+
+            LOADER_DIRS.iter().any(|dir| path.contains(dir))
+                || LOADER_MODULES.contains(&name.as_str())
+                // System copies of the proxied DLLs are loaded by everything
+                || (PROXY_MODULES.contains(&name.as_str()) && module.path.parent() == game_dir)
+        })
+        .map(|module| module.path)
+        .collect()
+}
+
+// The storefront SDK loaded by the game says the most; failing that, where
+// each store installs to by default
+pub(crate) fn distribution(pid: usize, image_path: &Path) -> Distribution {
+    const STEAM_MODULES: [&str; 2] = ["steam_api.dll", "steam_api64.dll"];
+    const EPIC_MODULES: [&str; 2] = ["eossdk-win32-shipping.dll", "eossdk-win64-shipping.dll"];
+    const STORE_MODULES: [&str; 2] = ["xgameruntime.dll", "microsoft.xbox.services.dll"];
+
+    match modules(pid) {
+        Ok(modules) => {
+            let loaded = |names: &[&str]| {
+                modules
+                    .iter()
+                    .any(|module| names.contains(&module.name.to_ascii_lowercase().as_str()))
+            };
+
+            if loaded(&STEAM_MODULES) {
+                return Distribution::Steam;
+            } else if loaded(&EPIC_MODULES) {
+                return Distribution::Epic;
+            } else if loaded(&STORE_MODULES) {
+                return Distribution::MicrosoftStore;
+            }
+        }
+        Err(e) => tracing::debug!("unable to check for storefront modules: {}", e),
+    }
+
+    let is_in = |dir: &str| {
+        image_path
+            .components()
+            .any(|component| component.as_os_str().eq_ignore_ascii_case(dir))
+    };
+
+    if is_in("steamapps") {
+        Distribution::Steam
+    } else if is_in("Epic Games") {
+        Distribution::Epic
+    } else if is_in("itch") {
+        Distribution::Itch
+    } else if is_in("WindowsApps") || is_in("XboxGames") {
+        Distribution::MicrosoftStore
+    } else {
+        Distribution::Other
+    }
+}
+
+// The Steam beta branch the game was installed from, read from the app
+// manifest next to the install; None for the public release or a non-Steam
+// copy
+pub(crate) fn steam_branch(image_path: &Path) -> Option<String> {
+    const APP_ID: u32 = 945360;
+
+    // steamapps/common/Among Us/Among Us.exe
+    let steamapps = image_path.parent()?.parent()?.parent()?;
+    let manifest = steamapps.join(format!("appmanifest_{}.acf", APP_ID));
+
+    let manifest = match std::fs::read_to_string(&manifest) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            tracing::trace!("no Steam manifest at {}: {}", manifest.display(), e);
+            return None;
+        }
+    };
+
+    // A line of the form: "BetaKey"    "public-beta"
+    manifest.lines().find_map(|line| {
+        let mut parts = line.split('"').filter(|part| !part.trim().is_empty());
+
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if key.eq_ignore_ascii_case("betakey") => {
+                Some(value.to_string()).filter(|value| !value.is_empty() && value != "public")
+            }
+            _ => None,
+        }
+    })
+}
+
+fn until_nul(wide: &[u16]) -> &[u16] {
+    let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+    &wide[..len]
+}
+
+pub(crate) unsafe fn image_path(process: HANDLE) -> Result<PathBuf> {
+    const MAX_PATH_LEN: usize = 1024;
+
+    let mut path: Vec<u16> = vec![0; MAX_PATH_LEN];
+    let mut len = path.len() as u32;
+
+    if QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut len) == 0 {
+        return Err(AttachError::QueryImage(GetLastError()).into());
+    }
+
+    path.truncate(len as usize);
+
+    Ok(OsString::from_wide(&path).into())
+}
+
+// Enough to tell simultaneous clients apart before attaching to one
+#[derive(Debug, Clone)]
+pub struct GameProcess {
+    pub pid: usize,
+    pub window_title: Option<String>,
+    // Windows account running the process, as DOMAIN\user
+    pub user: Option<String>,
+}
+
+pub fn list_games() -> Result<Vec<GameProcess>> {
+    Ok(find_game_pids()?
+        .into_iter()
+        .map(|pid| GameProcess {
+            pid,
+            window_title: Window::find(pid).and_then(|window| window.title()),
+            user: process_user(pid),
+        })
+        .collect())
+}
+
+pub fn process_user(pid: usize) -> Option<String> {
+    let process =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false.into(), pid as u32) };
+    if process.is_null() {
+        return None;
+    }
+
+    let mut token: HANDLE = std::ptr::null_mut();
+    let opened = unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) };
+    unsafe { CloseHandle(process) };
+
+    if opened == 0 {
+        return None;
+    }
+
+    let user = unsafe { token_user(token) };
+    unsafe { CloseHandle(token) };
+
+    user
+}
+
+unsafe fn token_user(token: HANDLE) -> Option<String> {
+    let mut len = 0;
+
+    // First call only reports the size of the user's SID
+    GetTokenInformation(token, TokenUser, std::ptr::null_mut(), 0, &mut len);
+
+    // usizes keep the buffer aligned for TOKEN_USER
+    let mut buffer: Vec<usize> = vec![0; (len as usize).div_ceil(size_of::<usize>())];
+    if GetTokenInformation(
+        token,
+        TokenUser,
+        buffer.as_mut_ptr() as *mut _,
+        len,
+        &mut len,
+    ) == 0
+    {
+        return None;
+    }
+
+    let sid = (*(buffer.as_ptr() as *const TOKEN_USER)).User.Sid;
+
+    let mut name: Vec<u16> = vec![0; 256];
+    let mut name_len = name.len() as u32;
+    let mut domain: Vec<u16> = vec![0; 256];
+    let mut domain_len = domain.len() as u32;
+    let mut sid_use = 0;
+
+    if LookupAccountSidW(
+        std::ptr::null(),
+        sid,
+        name.as_mut_ptr(),
+        &mut name_len,
+        domain.as_mut_ptr(),
+        &mut domain_len,
+        &mut sid_use,
+    ) == 0
+    {
+        return None;
+    }
+
+    Some(format!(
+        "{}\\{}",
+        String::from_utf16_lossy(&domain[..domain_len as usize]),
+        String::from_utf16_lossy(&name[..name_len as usize])
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    GameLaunched(usize),
+    GameClosed(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct ProcessWatcher {
+    known: Vec<usize>,
+}
+
+impl ProcessWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Games already running when the watcher starts are reported as launched on
+    // the first poll
+    pub fn poll(&mut self) -> Result<Vec<ProcessEvent>> {
+        let pids = find_game_pids()?;
+
+        let closed = self
+            .known
+            .iter()
+            .filter(|pid| !pids.contains(pid))
+            .map(|&pid| ProcessEvent::GameClosed(pid));
+        let launched = pids
+            .iter()
+            .filter(|pid| !self.known.contains(pid))
+            .map(|&pid| ProcessEvent::GameLaunched(pid));
+
+        let events = closed.chain(launched).collect();
+        self.known = pids;
+
+        Ok(events)
+    }
+
+    // Polls on a background thread until stopped, checking between polls; it
+    // also stops the next time it has an event to deliver after the receiver
+    // is dropped
+    pub fn spawn(interval: Duration, stop: StopHandle) -> (JoinHandle<()>, Receiver<ProcessEvent>) {
+        let (sender, receiver) = channel();
+
+        let handle = thread::spawn(move || {
+            let mut watcher = ProcessWatcher::new();
+
+            while !stop.is_stopped() {
+                match watcher.poll() {
+                    Ok(events) => {
+                        for event in events {
+                            if sender.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("failed to enumerate processes: {}", e),
+                }
+
+                thread::sleep(interval);
+            }
+        });
+
+        (handle, receiver)
+    }
+}
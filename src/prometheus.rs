@@ -0,0 +1,229 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{error::Error, game::Game, state::State};
+
+// Upper bounds in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+const PHASES: [&str; 4] = ["menu", "lobby", "in_game", "ended"];
+
+// Scrapes are answered one at a time, so a client that connects and goes
+// quiet only holds up the others this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// Longer request lines are cut off and won't match /metrics
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+// Poll metrics in the Prometheus text format; clones share the same metrics,
+// so one can be handed to a watcher and another to serve()
+#[derive(Debug, Clone, Default)]
+pub struct Exporter {
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_count: u64,
+    latency_sum: f64,
+    errors: BTreeMap<&'static str, u64>,
+    phase: Option<&'static str>,
+    reads: u64,
+    read_bytes: u64,
+    read_failures: u64,
+}
+
+impl Exporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_poll(&self, game: &Game, duration: Duration, result: Result<&State, &Error>) {
+        let stats = game.read_stats();
+
+        let mut metrics = match self.metrics.lock() {
+            Ok(metrics) => metrics,
+            Err(_) => return,
+        };
+
+        let seconds = duration.as_secs_f64();
+        for (count, bound) in metrics.latency_buckets.iter_mut().zip(&LATENCY_BUCKETS) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        metrics.latency_count += 1;
+        metrics.latency_sum += seconds;
+
+        match result {
+            Ok(state) => {
+                metrics.phase = Some(match state {
+                    State::Menu => "menu",
+                    State::Lobby { .. } => "lobby",
+                    State::InGame { .. } => "in_game",
+                    State::Ended { .. } => "ended",
+                })
+            }
+            Err(e) => *metrics.errors.entry(error_kind(e)).or_insert(0) += 1,
+        }
+
+        metrics.reads = stats.reads;
+        metrics.read_bytes = stats.bytes;
+        metrics.read_failures = stats.failures;
+    }
+
+    pub fn render(&self) -> String {
+        let metrics = match self.metrics.lock() {
+            Ok(metrics) => metrics,
+            Err(_) => return String::new(),
+        };
+
+        let mut out = String::new();
+
+        // Writing to a String can't fail
+        let _ = metrics.render(&mut out);
+
+        out
+    }
+
+    // Serves GET /metrics on a background thread until the process exits
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr)?;
+        let exporter = self.clone();
+
+        Ok(thread::spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| exporter.respond(stream));
+
+                if let Err(e) = result {
+                    tracing::debug!("metrics request failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+        let mut request_line = String::new();
+        BufReader::new(&stream)
+            .take(MAX_REQUEST_LINE)
+            .read_line(&mut request_line)?;
+
+        let path = request_line.split_whitespace().nth(1);
+
+        if request_line.starts_with("GET ") && path == Some("/metrics") {
+            let body = self.render();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        } else {
+            stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )
+        }
+    }
+}
+
+impl Metrics {
+    fn render(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(
+            out,
+            "# HELP taskinator_poll_duration_seconds Time taken by each state poll."
+        )?;
+        writeln!(out, "# TYPE taskinator_poll_duration_seconds histogram")?;
+        for (count, bound) in self.latency_buckets.iter().zip(&LATENCY_BUCKETS) {
+            writeln!(
+                out,
+                "taskinator_poll_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, count
+            )?;
+        }
+        writeln!(
+            out,
+            "taskinator_poll_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            self.latency_count
+        )?;
+        writeln!(
+            out,
+            "taskinator_poll_duration_seconds_sum {}",
+            self.latency_sum
+        )?;
+        writeln!(
+            out,
+            "taskinator_poll_duration_seconds_count {}",
+            self.latency_count
+        )?;
+
+        writeln!(
+            out,
+            "# HELP taskinator_poll_errors_total Failed polls by error kind."
+        )?;
+        writeln!(out, "# TYPE taskinator_poll_errors_total counter")?;
+        for (kind, count) in &self.errors {
+            writeln!(
+                out,
+                "taskinator_poll_errors_total{{kind=\"{}\"}} {}",
+                kind, count
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP taskinator_game_phase Phase seen by the last successful poll."
+        )?;
+        writeln!(out, "# TYPE taskinator_game_phase gauge")?;
+        for phase in &PHASES {
+            let value = if self.phase == Some(*phase) { 1 } else { 0 };
+            writeln!(
+                out,
+                "taskinator_game_phase{{phase=\"{}\"}} {}",
+                phase, value
+            )?;
+        }
+
+        writeln!(
+            out,
+            "# HELP taskinator_reads_total ReadProcessMemory calls issued."
+        )?;
+        writeln!(out, "# TYPE taskinator_reads_total counter")?;
+        writeln!(out, "taskinator_reads_total {}", self.reads)?;
+        writeln!(
+            out,
+            "# HELP taskinator_read_bytes_total Bytes read from the game."
+        )?;
+        writeln!(out, "# TYPE taskinator_read_bytes_total counter")?;
+        writeln!(out, "taskinator_read_bytes_total {}", self.read_bytes)?;
+        writeln!(
+            out,
+            "# HELP taskinator_read_failures_total ReadProcessMemory calls that failed."
+        )?;
+        writeln!(out, "# TYPE taskinator_read_failures_total counter")?;
+        writeln!(out, "taskinator_read_failures_total {}", self.read_failures)
+    }
+}
+
+fn error_kind(error: &Error) -> &'static str {
+    match error {
+        Error::Attach(_) => "attach",
+        Error::Read(_) => "read",
+        Error::Parse(_) => "parse",
+        Error::Unsupported(_) => "unsupported",
+        Error::ProcessGone => "process_gone",
+        Error::Timeout => "timeout",
+        Error::Cancelled => "cancelled",
+        Error::Remote(_) => "remote",
+        Error::UnsupportedBeta(_) => "unsupported_beta",
+    }
+}
@@ -0,0 +1,391 @@
+// Pushes states out to a collector, for when the machine next to the game sits
+// behind NAT and can't be reached by a RemoteGame. The collector is a
+// WebSocket (ws:// or wss://) taking one text message per PushMessage, or an
+// HTTP endpoint (http:// or https://) taking POSTs of a JSON array of them.
+// Either way each connection, or each POST, starts with a Hello.
+//
+// Every change is sent as a full Snapshot, so a collector that only reads those
+// still has everything; Events are the per-player changes between them.
+// Messages queue up while the collector can't be reached and go out in order
+// once it can, the oldest being dropped if the buffer fills.
+use std::{
+    collections::VecDeque,
+    io,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tungstenite::{
+    client::IntoClientRequest, http::HeaderValue, stream::MaybeTlsStream, Message, WebSocket,
+};
+
+use crate::{
+    events::{self, Event},
+    redact::Redaction,
+    state::{State, StateSource},
+};
+
+// Bumped whenever a message changes shape
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const DEFAULT_BUFFER: usize = 1024;
+const DEFAULT_RECONNECT: (Duration, Duration) = (Duration::from_secs(1), Duration::from_secs(30));
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// seq counts up from 0 for as long as the pusher runs, so a collector can spot
+// gaps and, over HTTP, the repeats from a POST that was retried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PushMessage {
+    Hello {
+        version: u32,
+    },
+    Snapshot {
+        seq: u64,
+        elapsed_ms: u64,
+        state: State,
+    },
+    Events {
+        seq: u64,
+        elapsed_ms: u64,
+        events: Vec<Event>,
+    },
+}
+
+pub struct Pusher {
+    url: String,
+    token: Option<String>,
+    redaction: Redaction,
+    buffer: usize,
+    reconnect: (Duration, Duration),
+}
+
+impl Pusher {
+    pub fn new(url: impl Into<String>) -> Self {
+        Pusher {
+            url: url.into(),
+            token: None,
+            redaction: Redaction::default(),
+            buffer: DEFAULT_BUFFER,
+            reconnect: DEFAULT_RECONNECT,
+        }
+    }
+
+    // Sent as a bearer token with the WebSocket handshake or each POST
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    // Applied to every state before it leaves the machine
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    // Messages kept while the collector is unreachable
+    pub fn buffer(mut self, capacity: usize) -> Self {
+        self.buffer = capacity.max(1);
+        self
+    }
+
+    // The wait after the first failure, doubling up to max for each one after
+    pub fn reconnect(mut self, min: Duration, max: Duration) -> Self {
+        self.reconnect = (min, max.max(min));
+        self
+    }
+
+    // Polls the source and pushes on a background thread until stopped; fails
+    // straight away only when the URL isn't one of the supported schemes
+    pub fn spawn(
+        self,
+        source: impl StateSource + Send + 'static,
+        interval: Duration,
+    ) -> io::Result<(JoinHandle<()>, PushHandle)> {
+        let scheme = self.url.split("://").next().unwrap_or_default();
+        if !["ws", "wss", "http", "https"].contains(&scheme.to_ascii_lowercase().as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "can't push to {}; expected a ws, wss, http or https URL",
+                    self.url
+                ),
+            ));
+        }
+
+        let handle = PushHandle::default();
+        let shared = handle.clone();
+
+        Ok((
+            thread::spawn(move || self.run(source, interval, shared)),
+            handle,
+        ))
+    }
+
+    fn run(self, source: impl StateSource, interval: Duration, handle: PushHandle) {
+        let started = Instant::now();
+        let mut queue = Queue {
+            messages: VecDeque::new(),
+            capacity: self.buffer,
+            seq: 0,
+            dropped: 0,
+        };
+        let mut last: Option<State> = None;
+        let mut link: Option<Link> = None;
+        let mut backoff = self.reconnect.0;
+        let mut retry_at = started;
+
+        while !handle.is_stopped() {
+            let tick = Instant::now();
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+
+            // Redacted before comparing, so changes to redacted fields alone
+            // don't push anything
+            let state = source.state().map(|mut state| {
+                self.redaction.apply(&mut state);
+                state
+            });
+
+            match state {
+                Ok(state) if last.as_ref() != Some(&state) => {
+                    if let Some(last) = &last {
+                        let events = events::diff(last, &state);
+                        if !events.is_empty() {
+                            queue.push(|seq| PushMessage::Events {
+                                seq,
+                                elapsed_ms,
+                                events,
+                            });
+                        }
+                    }
+                    queue.push(|seq| PushMessage::Snapshot {
+                        seq,
+                        elapsed_ms,
+                        state: state.clone(),
+                    });
+                    last = Some(state);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::trace!("push source read failed: {}", e),
+            }
+
+            if link.is_none() && tick >= retry_at {
+                match Link::open(&self.url, self.token.as_deref()) {
+                    Ok(opened) => {
+                        backoff = self.reconnect.0;
+                        link = Some(opened);
+
+                        // So a collector that's just come up isn't left
+                        // waiting for the next change
+                        if let (true, Some(state)) = (queue.messages.is_empty(), &last) {
+                            queue.push(|seq| PushMessage::Snapshot {
+                                seq,
+                                elapsed_ms,
+                                state: state.clone(),
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        tracing::debug!("couldn't reach the collector at {}: {}", self.url, e);
+                        retry_at = tick + backoff;
+                        backoff = (backoff * 2).min(self.reconnect.1);
+                    }
+                }
+            }
+
+            if let Some(open) = &mut link {
+                if let Err(e) = open.flush(&mut queue.messages) {
+                    tracing::debug!("lost the collector at {}: {}", self.url, e);
+                    link = None;
+                    retry_at = Instant::now() + backoff;
+                }
+            }
+
+            handle.0.connected.store(link.is_some(), Ordering::Relaxed);
+            handle.0.dropped.store(queue.dropped, Ordering::Relaxed);
+            handle
+                .0
+                .buffered
+                .store(queue.messages.len() as u64, Ordering::Relaxed);
+
+            thread::sleep(interval.saturating_sub(tick.elapsed()));
+        }
+
+        if let Some(Link::WebSocket(mut socket)) = link {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+        }
+    }
+}
+
+// Stops the pusher and says how it's getting on
+#[derive(Debug, Clone, Default)]
+pub struct PushHandle(Arc<Progress>);
+
+#[derive(Debug, Default)]
+struct Progress {
+    stop: AtomicBool,
+    connected: AtomicBool,
+    buffered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl PushHandle {
+    // Takes effect by the end of the current interval
+    pub fn stop(&self) {
+        self.0.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.stop.load(Ordering::Relaxed)
+    }
+
+    // As of the last interval
+    pub fn is_connected(&self) -> bool {
+        self.0.connected.load(Ordering::Relaxed)
+    }
+
+    // Messages waiting for the collector
+    pub fn buffered(&self) -> u64 {
+        self.0.buffered.load(Ordering::Relaxed)
+    }
+
+    // Messages thrown away because the buffer was full
+    pub fn dropped(&self) -> u64 {
+        self.0.dropped.load(Ordering::Relaxed)
+    }
+}
+
+struct Queue {
+    messages: VecDeque<PushMessage>,
+    capacity: usize,
+    seq: u64,
+    dropped: u64,
+}
+
+impl Queue {
+    // Drops from the front to make room
+    fn push(&mut self, message: impl FnOnce(u64) -> PushMessage) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+            self.dropped += 1;
+        }
+
+        self.messages.push_back(message(self.seq));
+        self.seq += 1;
+    }
+}
+
+enum Link {
+    WebSocket(Box<WebSocket<MaybeTlsStream<TcpStream>>>),
+    Http {
+        agent: ureq::Agent,
+        url: String,
+        token: Option<String>,
+    },
+}
+
+impl Link {
+    fn open(url: &str, token: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            let agent = ureq::AgentBuilder::new()
+                .tls_connector(Arc::new(native_tls::TlsConnector::new()?))
+                .timeout(HTTP_TIMEOUT)
+                .build();
+
+            return Ok(Link::Http {
+                agent,
+                url: url.to_string(),
+                token: token.map(String::from),
+            });
+        }
+
+        let mut request = url.into_client_request()?;
+        if let Some(token) = token {
+            request.headers_mut().insert(
+                "Authorization",
+                HeaderValue::from_str(&format!("Bearer {}", token))?,
+            );
+        }
+
+        let (mut socket, _) = tungstenite::connect(request)?;
+
+        // Reads only ever look for pings and closes, so they shouldn't hold up
+        // the next poll
+        let stream = match socket.get_ref() {
+            MaybeTlsStream::Plain(stream) => stream,
+            MaybeTlsStream::NativeTls(stream) => stream.get_ref(),
+            _ => return Err("unsupported stream".into()),
+        };
+        stream.set_read_timeout(Some(Duration::from_millis(1)))?;
+
+        socket.send(Message::Text(serde_json::to_string(&PushMessage::Hello {
+            version: PROTOCOL_VERSION,
+        })?))?;
+
+        Ok(Link::WebSocket(Box::new(socket)))
+    }
+
+    // Sends what it can from the front of the queue; what's left goes on the
+    // next flush, over this link or the next one
+    fn flush(
+        &mut self,
+        messages: &mut VecDeque<PushMessage>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Link::WebSocket(socket) => {
+                while let Some(message) = messages.front() {
+                    socket.send(Message::Text(serde_json::to_string(message)?))?;
+                    messages.pop_front();
+                }
+
+                loop {
+                    match socket.read() {
+                        Ok(Message::Close(_)) => {
+                            return Err("the collector closed the connection".into())
+                        }
+                        Ok(_) => {}
+                        Err(tungstenite::Error::Io(e))
+                            if matches!(
+                                e.kind(),
+                                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                            ) =>
+                        {
+                            break
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+
+                // Any pongs queued by reading
+                socket.flush()?;
+            }
+            Link::Http { agent, url, token } => {
+                if messages.is_empty() {
+                    return Ok(());
+                }
+
+                let hello = PushMessage::Hello {
+                    version: PROTOCOL_VERSION,
+                };
+                let body: Vec<&PushMessage> =
+                    std::iter::once(&hello).chain(messages.iter()).collect();
+
+                let mut request = agent.post(url).set("Content-Type", "application/json");
+                if let Some(token) = token {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+                request.send_bytes(&serde_json::to_vec(&body)?)?;
+
+                messages.clear();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+// Python bindings, built as the taskinator_communicator extension module;
+// states and events come out as plain dicts and lists so they drop straight
+// into a DataFrame
+use std::{thread::JoinHandle, time::Duration};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+};
+
+use crate::{
+    error::Error,
+    events::Event,
+    game::Game,
+    state::State,
+    watcher::{GameWatcher, StopHandle},
+};
+
+create_exception!(taskinator_communicator, TaskinatorError, PyException);
+
+// How often a blocked iterator wakes up to check for Ctrl-C
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+fn to_py_err(error: Error) -> PyErr {
+    TaskinatorError::new_err(error.to_string())
+}
+
+#[pyclass(name = "Game", module = "taskinator_communicator", frozen)]
+pub struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    #[staticmethod]
+    fn find(py: Python<'_>) -> PyResult<Self> {
+        let game = py.allow_threads(Game::find).map_err(to_py_err)?;
+        Ok(PyGame { game })
+    }
+
+    #[staticmethod]
+    fn from_pid(py: Python<'_>, pid: usize) -> PyResult<Self> {
+        let game = py
+            .allow_threads(|| Game::from_pid(pid))
+            .map_err(to_py_err)?;
+        Ok(PyGame { game })
+    }
+
+    #[getter]
+    fn pid(&self) -> usize {
+        self.game.pid()
+    }
+
+    fn is_running(&self) -> bool {
+        self.game.is_running()
+    }
+
+    fn state(&self, py: Python<'_>) -> PyResult<PyState> {
+        let state = py.allow_threads(|| self.game.state()).map_err(to_py_err)?;
+        Ok(PyState { state })
+    }
+}
+
+#[pyclass(name = "State", module = "taskinator_communicator", frozen)]
+pub struct PyState {
+    state: State,
+}
+
+#[pymethods]
+impl PyState {
+    // "menu", "lobby", "in_game" or "ended"
+    #[getter]
+    fn phase(&self) -> &'static str {
+        match self.state {
+            State::Menu => "menu",
+            State::Lobby { .. } => "lobby",
+            State::InGame { .. } => "in_game",
+            State::Ended { .. } => "ended",
+        }
+    }
+
+    #[getter]
+    fn meeting(&self) -> Option<String> {
+        match &self.state {
+            State::InGame { meeting, .. } => Some(meeting.to_string()),
+            _ => None,
+        }
+    }
+
+    #[getter]
+    fn tasks(&self) -> Option<(u32, u32)> {
+        match &self.state {
+            State::InGame {
+                tasks_completed,
+                tasks_total,
+                ..
+            } => Some((*tasks_completed, *tasks_total)),
+            _ => None,
+        }
+    }
+
+    // One dict per player
+    #[getter]
+    fn players<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize::pythonize(py, self.state.players())?)
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize::pythonize(py, &self.state)?)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+
+    fn __str__(&self) -> String {
+        self.state.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<State {}>", self.phase())
+    }
+}
+
+// Iterates over events from a watcher thread polling a game of its own;
+// iteration ends once the thread stops
+#[pyclass(name = "Watcher", module = "taskinator_communicator")]
+pub struct PyWatcher {
+    receiver: Receiver<Event>,
+    stop: StopHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[pymethods]
+impl PyWatcher {
+    #[new]
+    #[pyo3(signature = (pid = None, interval = 0.1))]
+    fn new(py: Python<'_>, pid: Option<usize>, interval: f64) -> PyResult<Self> {
+        let interval = Duration::try_from_secs_f64(interval).map_err(|_| {
+            PyValueError::new_err("interval must be a non-negative number of seconds")
+        })?;
+
+        let game = py
+            .allow_threads(|| match pid {
+                Some(pid) => Game::from_pid(pid),
+                None => Game::find(),
+            })
+            .map_err(to_py_err)?;
+
+        let watcher = GameWatcher::new(game);
+        let stop = watcher.stop_handle();
+        let (thread, receiver) = watcher.spawn_thread(interval);
+
+        Ok(PyWatcher {
+            receiver,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn stop(&mut self, py: Python<'_>) {
+        self.stop.stop();
+
+        if let Some(thread) = self.thread.take() {
+            let receiver = &self.receiver;
+
+            // A panic on the watcher thread has already been reported there
+            let _ = py.allow_threads(|| {
+                // Events nobody will read, drained so a full channel can't
+                // keep the thread from seeing the stop
+                while !thread.is_finished() {
+                    let _ = receiver.recv_timeout(SIGNAL_CHECK_INTERVAL);
+                }
+                thread.join()
+            });
+        }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        loop {
+            match py.allow_threads(|| self.receiver.recv_timeout(SIGNAL_CHECK_INTERVAL)) {
+                Ok(event) => return Ok(Some(pythonize::pythonize(py, &event)?)),
+                Err(RecvTimeoutError::Timeout) => py.check_signals()?,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Drop for PyWatcher {
+    fn drop(&mut self) {
+        self.stop.stop();
+    }
+}
+
+#[pymodule]
+fn taskinator_communicator(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()?;
+    m.add_class::<PyState>()?;
+    m.add_class::<PyWatcher>()?;
+    m.add("TaskinatorError", m.py().get_type::<TaskinatorError>())?;
+    Ok(())
+}
@@ -0,0 +1,321 @@
+// Session recordings: every state read, one JSON line each, with the time since
+// the recording started, so a match can be looked at again without the game.
+// With the compression feature, files ending in .zst are zstd compressed as
+// they're written; an evening at 10 Hz is otherwise hundreds of megabytes.
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::State;
+
+#[cfg(feature = "compression")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    // Since the recording started
+    pub elapsed: Duration,
+    pub state: State,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line {
+    elapsed_ms: u64,
+    state: State,
+}
+
+pub struct Recorder<W: Write> {
+    writer: W,
+    started: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(writer: W) -> Self {
+        Recorder {
+            writer,
+            started: Instant::now(),
+        }
+    }
+
+    // Not flushed, so a compressed stream isn't cut into tiny blocks; call
+    // flush or drop the recorder to get it all out
+    pub fn record(&mut self, state: &State) -> io::Result<()> {
+        let line = Line {
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            state: state.clone(),
+        };
+
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl Recorder<Box<dyn Write + Send>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)?;
+
+        #[cfg(feature = "compression")]
+        if is_compressed(path) {
+            let encoder = zstd::Encoder::new(file, COMPRESSION_LEVEL)?.auto_finish();
+            return Ok(Recorder::new(Box::new(BufWriter::new(encoder))));
+        }
+
+        Ok(Recorder::new(Box::new(BufWriter::new(file))))
+    }
+}
+
+pub struct Replay<R: BufRead> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> Replay<R> {
+    pub fn new(reader: R) -> Self {
+        Replay {
+            reader,
+            line: String::new(),
+        }
+    }
+}
+
+impl Replay<Box<dyn BufRead + Send>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+
+        #[cfg(feature = "compression")]
+        if is_compressed(path) {
+            let decoder = zstd::Decoder::new(file)?;
+            return Ok(Replay::new(Box::new(BufReader::new(decoder))));
+        }
+
+        Ok(Replay::new(Box::new(BufReader::new(file))))
+    }
+}
+
+impl<R: BufRead> Iterator for Replay<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.line.clear();
+
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => None,
+            Ok(_) => Some(
+                serde_json::from_str(&self.line)
+                    .map(|line: Line| Frame {
+                        elapsed: Duration::from_millis(line.elapsed_ms),
+                        state: line.state,
+                    })
+                    .map_err(io::Error::from),
+            ),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+// Plays a recording back like a video: paused, sped up or slowed down, and
+// seeked both ways. The whole recording is read in up front, since a
+// compressed one can't be seeked.
+pub struct Playback {
+    frames: Vec<Frame>,
+    // Index of the frame next_frame hands out next
+    next: usize,
+    // Where playback was at the anchor, in recording time
+    position: Duration,
+    // When playback was last anchored; None while paused
+    anchor: Option<Instant>,
+    speed: f64,
+}
+
+impl Playback {
+    // Starts paused at the beginning
+    pub fn new(frames: impl IntoIterator<Item = io::Result<Frame>>) -> io::Result<Self> {
+        Ok(Playback {
+            frames: frames.into_iter().collect::<io::Result<_>>()?,
+            next: 0,
+            position: Duration::from_secs(0),
+            anchor: None,
+            speed: 1.0,
+        })
+    }
+
+    pub fn position(&self) -> Duration {
+        match self.anchor {
+            Some(anchor) => self.position + anchor.elapsed().mul_f64(self.speed),
+            None => self.position,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map_or(Duration::from_secs(0), |frame| frame.elapsed)
+    }
+
+    // The latest frame at or before the current position
+    pub fn current(&self) -> Option<&Frame> {
+        let position = self.position();
+        let after = self
+            .frames
+            .partition_point(|frame| frame.elapsed <= position);
+
+        self.frames[..after].last()
+    }
+
+    // next_frame carries on from the frame current() returns there
+    pub fn seek_to(&mut self, timestamp: Duration) {
+        self.position = timestamp.min(self.duration());
+        if self.anchor.is_some() {
+            self.anchor = Some(Instant::now());
+        }
+
+        let after = self
+            .frames
+            .partition_point(|frame| frame.elapsed <= self.position);
+        self.next = after.saturating_sub(1);
+    }
+
+    pub fn pause(&mut self) {
+        self.position = self.position();
+        self.anchor = None;
+    }
+
+    pub fn resume(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = Some(Instant::now());
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.anchor.is_none()
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    // 2.0 plays at double speed; anything but a positive, finite speed is
+    // ignored
+    pub fn set_speed(&mut self, speed: f64) {
+        if !(speed > 0.0 && speed.is_finite()) {
+            return;
+        }
+
+        if self.anchor.is_some() {
+            self.position = self.position();
+            self.anchor = Some(Instant::now());
+        }
+        self.speed = speed;
+    }
+
+    // Waits until the next frame is due, then returns it; None at the end of
+    // the recording or while paused
+    pub fn next_frame(&mut self) -> Option<&Frame> {
+        if self.is_paused() {
+            return None;
+        }
+
+        let due = self.frames.get(self.next)?.elapsed;
+        let position = self.position();
+        if due > position {
+            thread::sleep((due - position).div_f64(self.speed));
+        }
+
+        self.next += 1;
+        self.frames.get(self.next - 1)
+    }
+}
+
+#[cfg(feature = "compression")]
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "zst")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Player;
+
+    fn lobby(count: u8) -> State {
+        State::Lobby {
+            players: (0..count).map(|id| Player::test(id, "Red")).collect(),
+        }
+    }
+
+    // One frame a second, each lobby a player bigger than the last
+    fn playback() -> Playback {
+        Playback::new((0..3).map(|i| {
+            Ok(Frame {
+                elapsed: Duration::from_secs(u64::from(i)),
+                state: lobby(i),
+            })
+        }))
+        .unwrap()
+    }
+
+    fn players(frame: Option<&Frame>) -> Option<usize> {
+        frame.map(|frame| frame.state.players().len())
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let mut recorder = Recorder::new(Vec::new());
+        recorder.record(&State::Menu).unwrap();
+        recorder.record(&lobby(2)).unwrap();
+
+        let frames: Vec<Frame> = Replay::new(&recorder.into_inner()[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].state, State::Menu);
+        assert_eq!(frames[1].state, lobby(2));
+    }
+
+    #[test]
+    fn seeks_both_ways() {
+        let mut playback = playback();
+        assert_eq!(playback.duration(), Duration::from_secs(2));
+        assert_eq!(players(playback.current()), Some(0));
+
+        playback.seek_to(Duration::from_millis(1500));
+        assert_eq!(playback.position(), Duration::from_millis(1500));
+        assert_eq!(players(playback.current()), Some(1));
+
+        playback.seek_to(Duration::from_secs(60));
+        assert_eq!(playback.position(), Duration::from_secs(2));
+        assert_eq!(players(playback.current()), Some(2));
+
+        playback.seek_to(Duration::from_millis(500));
+        assert_eq!(players(playback.current()), Some(0));
+    }
+
+    #[test]
+    fn carries_on_from_the_seeked_frame() {
+        let mut playback = playback();
+        assert_eq!(players(playback.next_frame()), None);
+
+        playback.seek_to(Duration::from_millis(1500));
+        playback.resume();
+        assert_eq!(players(playback.next_frame()), Some(1));
+
+        playback.seek_to(Duration::from_secs(2));
+        assert_eq!(players(playback.next_frame()), Some(2));
+        assert_eq!(players(playback.next_frame()), None);
+    }
+}
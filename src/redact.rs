@@ -0,0 +1,195 @@
+// Hides spoilers from a state before it's shown to someone who shouldn't see
+// them, so one watcher can feed both a private admin view and a public overlay.
+// Each consumer applies its own Redaction to the states it's handed.
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant},
+};
+
+use crate::state::{MeetingState, State};
+
+// Deaths this soon after a meeting are taken to be the ejection everyone
+// watched; the shortest kill cooldown the game allows is longer
+const EJECTION_WINDOW: Duration = Duration::from_secs(10);
+
+// The default hides nothing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redaction {
+    // Report everyone as a crewmate
+    pub impostors: bool,
+    // Drop the fields read through offset packs, which are mostly mod roles
+    pub roles: bool,
+}
+
+impl Redaction {
+    // Everything players in the lobby couldn't see for themselves
+    pub fn public() -> Self {
+        Redaction {
+            impostors: true,
+            roles: true,
+        }
+    }
+
+    pub fn apply(&self, state: &mut State) {
+        let players = match state {
+            State::Menu => return,
+            State::Lobby { players } | State::InGame { players, .. } | State::Ended { players } => {
+                players
+            }
+        };
+
+        for player in players {
+            if self.impostors {
+                player.impostor = false;
+            }
+            if self.roles {
+                player.extra_roles.clear();
+            }
+        }
+    }
+
+    pub fn redacted(&self, state: &State) -> State {
+        let mut state = state.clone();
+        self.apply(&mut state);
+        state
+    }
+}
+
+// Shows deaths only once living players would know about them: at the next
+// meeting, or when someone is ejected. Unlike Redaction it has to see every
+// state in order, so keep one per consumer and feed it each read.
+#[derive(Debug, Clone, Default)]
+pub struct LivingView {
+    known_dead: BTreeSet<u8>,
+    in_meeting: bool,
+    meeting_ended: Option<Instant>,
+}
+
+impl LivingView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, state: &mut State, now: Instant) {
+        let (meeting, players) = match state {
+            State::InGame {
+                meeting, players, ..
+            } => (*meeting, players),
+            // The end screen shows everyone's fate, and they're all alive
+            // again next game
+            State::Menu | State::Lobby { .. } | State::Ended { .. } => {
+                *self = Self::default();
+                return;
+            }
+        };
+
+        let in_meeting = meeting != MeetingState::Proceeding;
+        if self.in_meeting && !in_meeting {
+            self.meeting_ended = Some(now);
+        }
+        self.in_meeting = in_meeting;
+
+        let ejecting = self
+            .meeting_ended
+            .is_some_and(|ended| now.saturating_duration_since(ended) < EJECTION_WINDOW);
+
+        for player in players {
+            if player.dead && (in_meeting || ejecting) {
+                self.known_dead.insert(player.id);
+            }
+
+            player.dead = self.known_dead.contains(&player.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{packs::RoleValue, state::Player};
+
+    fn in_game(meeting: MeetingState, dead: bool) -> State {
+        let mut player = Player::test(0, "Red");
+        player.dead = dead;
+
+        State::InGame {
+            meeting,
+            players: vec![player, Player::test(1, "Blue")],
+            tasks_completed: 0,
+            tasks_total: 10,
+        }
+    }
+
+    fn dead(state: &State) -> Vec<u8> {
+        state
+            .players()
+            .iter()
+            .filter(|player| player.dead)
+            .map(|player| player.id)
+            .collect()
+    }
+
+    #[test]
+    fn public_hides_impostors_and_roles() {
+        let mut impostor = Player::test(0, "Red");
+        impostor.impostor = true;
+        impostor
+            .extra_roles
+            .insert("sheriff".to_string(), RoleValue::Bool(true));
+        let state = State::Ended {
+            players: vec![impostor],
+        };
+
+        let redacted = Redaction::public().redacted(&state);
+        assert!(!redacted.players()[0].impostor);
+        assert!(redacted.players()[0].extra_roles.is_empty());
+
+        assert_eq!(Redaction::default().redacted(&state), state);
+    }
+
+    #[test]
+    fn deaths_show_at_the_next_meeting() {
+        let mut view = LivingView::new();
+        let start = Instant::now();
+
+        let mut state = in_game(MeetingState::Proceeding, true);
+        view.apply(&mut state, start);
+        assert!(dead(&state).is_empty());
+
+        let mut state = in_game(MeetingState::Discussion, true);
+        view.apply(&mut state, start + Duration::from_secs(30));
+        assert_eq!(dead(&state), vec![0]);
+
+        let mut state = in_game(MeetingState::Proceeding, true);
+        view.apply(&mut state, start + Duration::from_secs(120));
+        assert_eq!(dead(&state), vec![0]);
+    }
+
+    #[test]
+    fn ejections_show_straight_away() {
+        let mut view = LivingView::new();
+        let start = Instant::now();
+
+        let mut state = in_game(MeetingState::Results, false);
+        view.apply(&mut state, start);
+
+        let mut state = in_game(MeetingState::Proceeding, true);
+        view.apply(&mut state, start + Duration::from_secs(2));
+        assert_eq!(dead(&state), vec![0]);
+    }
+
+    #[test]
+    fn forgets_deaths_after_the_game() {
+        let mut view = LivingView::new();
+        let start = Instant::now();
+
+        view.apply(&mut in_game(MeetingState::Discussion, true), start);
+        view.apply(&mut State::Menu, start);
+
+        // A kill in the next game, before anyone could know
+        let mut state = in_game(MeetingState::Proceeding, true);
+        view.apply(&mut state, start + Duration::from_secs(60));
+        assert!(dead(&state).is_empty());
+    }
+}
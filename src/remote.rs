@@ -0,0 +1,476 @@
+// Reading the game from another machine: an Agent next to the game answers
+// state requests over TCP, and a RemoteGame anywhere else asks for them.
+// Messages are JSON, one per line. Without the remote-tls feature there's no
+// encryption, so anything beyond a trusted LAN should go through a tunnel,
+// e.g. ssh -L.
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+#[cfg(windows)]
+use std::{
+    collections::HashMap,
+    net::TcpListener,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(windows)]
+use crate::{error::Error, game::Game, redact::Redaction, watcher::StopHandle};
+use crate::{
+    error::RemoteError,
+    state::{State, StateSource},
+    wire::{receive, send},
+    Result,
+};
+
+// How often serve_until checks whether it's been stopped between connections
+#[cfg(windows)]
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// How long a new connection has to finish the TLS handshake and say Hello
+// before it's dropped, so idle sockets can't pile up threads
+#[cfg(windows)]
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Either side of a connection, plain or wrapped in TLS
+trait Stream: Read + Write + Send {}
+
+impl<S: Read + Write + Send> Stream for S {}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    // Must come first on every connection
+    Hello { token: Option<String> },
+    State,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    Ready,
+    State(State),
+    Error {
+        message: String,
+        transient: bool,
+        fatal: bool,
+    },
+}
+
+#[cfg(windows)]
+impl Response {
+    fn from_error(e: &Error) -> Self {
+        Response::Error {
+            message: e.to_string(),
+            transient: e.is_transient(),
+            fatal: e.is_fatal(),
+        }
+    }
+}
+
+// Serves state requests for one game; every connection gets its own thread
+#[cfg(windows)]
+pub struct Agent {
+    game: Arc<Game>,
+    token: Option<String>,
+    redaction: Redaction,
+    // Tokens that get a redaction of their own
+    clients: HashMap<String, Redaction>,
+    #[cfg(feature = "remote-tls")]
+    tls: Option<native_tls::TlsAcceptor>,
+}
+
+#[cfg(windows)]
+impl Agent {
+    pub fn new(game: Game) -> Self {
+        Agent {
+            game: Arc::new(game),
+            token: None,
+            redaction: Redaction::public(),
+            clients: HashMap::new(),
+            #[cfg(feature = "remote-tls")]
+            tls: None,
+        }
+    }
+
+    // Require clients to present this token before they're answered
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    // Applied to every state sent to a connection without a client token of
+    // its own; Redaction::public() unless set
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    // Connections presenting this token get their states through this
+    // redaction instead, e.g. an overlay left open and an admin panel let in
+    // with Redaction::default(). Once there are client tokens, connections
+    // need one of them or the token set with token().
+    pub fn client(mut self, token: impl Into<String>, redaction: Redaction) -> Self {
+        self.clients.insert(token.into(), redaction);
+        self
+    }
+
+    // Only accept TLS connections, e.g. to send tokens and states across the
+    // internet without a tunnel
+    #[cfg(feature = "remote-tls")]
+    pub fn tls(mut self, acceptor: native_tls::TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    // Accepts connections on a background thread until the process exits
+    pub fn serve(self, addr: impl ToSocketAddrs) -> io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr)?;
+        let agent = Arc::new(self);
+
+        Ok(thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => agent.spawn_connection(stream),
+                    Err(e) => tracing::debug!("agent accept failed: {}", e),
+                }
+            }
+        }))
+    }
+
+    // Like serve, but stops accepting and frees the address once stopped;
+    // connections already open carry on until their clients hang up
+    pub fn serve_until(
+        self,
+        addr: impl ToSocketAddrs,
+        stop: StopHandle,
+    ) -> io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let agent = Arc::new(self);
+
+        Ok(thread::spawn(move || {
+            while !stop.is_stopped() {
+                match listener.accept() {
+                    // Accepted sockets inherit the listener's non-blocking mode
+                    Ok((stream, _)) => match stream.set_nonblocking(false) {
+                        Ok(()) => agent.spawn_connection(stream),
+                        Err(e) => tracing::debug!("agent accept failed: {}", e),
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL_INTERVAL)
+                    }
+                    Err(e) => tracing::debug!("agent accept failed: {}", e),
+                }
+            }
+        }))
+    }
+
+    fn spawn_connection(self: &Arc<Self>, stream: TcpStream) {
+        let agent = self.clone();
+
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+
+            if let Err(e) = agent.handle(stream) {
+                tracing::debug!("agent connection from {:?} failed: {}", peer, e);
+            }
+        });
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+        // Shares the socket, so the timeout can be lifted once past the TLS
+        // layer
+        let socket = stream.try_clone()?;
+
+        #[cfg(feature = "remote-tls")]
+        if let Some(acceptor) = &self.tls {
+            let stream = acceptor
+                .accept(stream)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            return self.converse(BufReader::new(stream), &socket);
+        }
+
+        self.converse(BufReader::new(stream), &socket)
+    }
+
+    fn converse(&self, mut stream: BufReader<impl Stream>, socket: &TcpStream) -> io::Result<()> {
+        let redaction = match receive(&mut stream)? {
+            Some(Request::Hello { token }) => match self.find_client(token.as_deref()) {
+                Some(redaction) => redaction,
+                None if self.admits(token.as_deref()) => &self.redaction,
+                None => {
+                    return send(
+                        stream.get_mut(),
+                        &Response::Error {
+                            message: "wrong token".to_string(),
+                            transient: false,
+                            fatal: true,
+                        },
+                    )
+                }
+            },
+            Some(request) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected Hello, got {:?}", request),
+                ))
+            }
+            None => return Ok(()),
+        };
+
+        // Clients poll at their own pace once they're in
+        socket.set_read_timeout(None)?;
+        send(stream.get_mut(), &Response::Ready)?;
+
+        while let Some(request) = receive(&mut stream)? {
+            let response = match request {
+                Request::State => match self.game.state() {
+                    Ok(mut state) => {
+                        redaction.apply(&mut state);
+                        Response::State(state)
+                    }
+                    Err(e) => Response::from_error(&e),
+                },
+                Request::Hello { .. } => Response::Ready,
+            };
+
+            send(stream.get_mut(), &response)?;
+        }
+
+        Ok(())
+    }
+
+    // Whether a connection without a client token of its own is let in
+    fn admits(&self, token: Option<&str>) -> bool {
+        match (token, &self.token) {
+            (Some(token), Some(expected)) => same_token(token, expected),
+            // Anonymous access only for an agent with no tokens at all
+            (None, None) => self.clients.is_empty(),
+            _ => false,
+        }
+    }
+
+    // Checks every client token, so how long it takes doesn't say how close a
+    // guess was
+    fn find_client(&self, token: Option<&str>) -> Option<&Redaction> {
+        let token = token?;
+
+        self.clients
+            .iter()
+            .fold(None, |found, (client, redaction)| {
+                match same_token(token, client) {
+                    true => found.or(Some(redaction)),
+                    false => found,
+                }
+            })
+    }
+}
+
+// Compares every byte rather than stopping at the first difference, and goes
+// over the presented token whatever the expected one's length, so the timing
+// gives away neither
+#[cfg(windows)]
+fn same_token(presented: &str, expected: &str) -> bool {
+    let expected = expected.as_bytes();
+    let mut diff = presented.len() ^ expected.len();
+
+    for (i, byte) in presented.bytes().enumerate() {
+        let other = match expected.len() {
+            0 => 0,
+            len => expected[i % len],
+        };
+        diff |= usize::from(byte ^ other);
+    }
+
+    diff == 0
+}
+
+// Reads the state through an Agent; the connection is re-established on the
+// next call after it drops
+pub struct RemoteGame {
+    addrs: Vec<SocketAddr>,
+    token: Option<String>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "remote-tls")]
+    tls: Option<(native_tls::TlsConnector, String)>,
+    connection: Mutex<Option<Connection>>,
+}
+
+struct Connection {
+    stream: BufReader<Box<dyn Stream>>,
+    // The same socket as under stream, for changing timeouts
+    socket: TcpStream,
+}
+
+impl RemoteGame {
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        Self::connect_with_token(addr, None)
+    }
+
+    pub fn connect_with_token(addr: impl ToSocketAddrs, token: Option<String>) -> Result<Self> {
+        Self::new(addr, token)?.start()
+    }
+
+    // For an agent serving TLS; domain is checked against its certificate
+    #[cfg(feature = "remote-tls")]
+    pub fn connect_tls(
+        addr: impl ToSocketAddrs,
+        token: Option<String>,
+        connector: native_tls::TlsConnector,
+        domain: impl Into<String>,
+    ) -> Result<Self> {
+        let mut game = Self::new(addr, token)?;
+        game.tls = Some((connector, domain.into()));
+        game.start()
+    }
+
+    fn new(addr: impl ToSocketAddrs, token: Option<String>) -> Result<Self> {
+        let addrs = addr
+            .to_socket_addrs()
+            .map_err(RemoteError::Connection)?
+            .collect();
+
+        Ok(RemoteGame {
+            addrs,
+            token,
+            timeout: None,
+            #[cfg(feature = "remote-tls")]
+            tls: None,
+            connection: Mutex::new(None),
+        })
+    }
+
+    // Fail now rather than on the first state() if the agent isn't there
+    fn start(self) -> Result<Self> {
+        let connection = self.open()?;
+        *self.lock() = Some(connection);
+
+        Ok(self)
+    }
+
+    // Applies to each request and to reconnecting; None waits forever
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.timeout = timeout;
+
+        if let Some(connection) = self.lock().as_ref() {
+            connection
+                .socket
+                .set_read_timeout(timeout)
+                .and_then(|()| connection.socket.set_write_timeout(timeout))
+                .map_err(RemoteError::Connection)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn state(&self) -> Result<State> {
+        let mut connection = self.lock();
+
+        let result = match connection.take() {
+            Some(existing) => Ok(existing),
+            None => self.open(),
+        }
+        .and_then(|mut existing| {
+            let response = existing.request(&Request::State)?;
+            *connection = Some(existing);
+            Ok(response)
+        });
+
+        match result? {
+            Response::State(state) => Ok(state),
+            Response::Error {
+                message,
+                transient,
+                fatal,
+            } => Err(RemoteError::Agent {
+                message,
+                transient,
+                fatal,
+            }
+            .into()),
+            response => Err(RemoteError::Protocol(format!("{:?}", response)).into()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Connection>> {
+        // A panic mid-request leaves nothing worth keeping
+        self.connection.lock().unwrap_or_else(|poisoned| {
+            let mut connection = poisoned.into_inner();
+            *connection = None;
+            connection
+        })
+    }
+
+    fn open(&self) -> Result<Connection> {
+        let stream = match self.timeout {
+            Some(timeout) => self
+                .addrs
+                .iter()
+                .find_map(|addr| TcpStream::connect_timeout(addr, timeout).ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "couldn't reach the agent")),
+            None => TcpStream::connect(&self.addrs[..]),
+        }
+        .and_then(|stream| {
+            stream.set_read_timeout(self.timeout)?;
+            stream.set_write_timeout(self.timeout)?;
+            Ok(stream)
+        })
+        .map_err(RemoteError::Connection)?;
+
+        let socket = stream.try_clone().map_err(RemoteError::Connection)?;
+
+        #[cfg(feature = "remote-tls")]
+        let stream: Box<dyn Stream> = match &self.tls {
+            Some((connector, domain)) => Box::new(
+                connector
+                    .connect(domain, stream)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+                    .map_err(RemoteError::Connection)?,
+            ),
+            None => Box::new(stream),
+        };
+        #[cfg(not(feature = "remote-tls"))]
+        let stream: Box<dyn Stream> = Box::new(stream);
+
+        let mut connection = Connection {
+            stream: BufReader::new(stream),
+            socket,
+        };
+
+        match connection.request(&Request::Hello {
+            token: self.token.clone(),
+        })? {
+            Response::Ready => Ok(connection),
+            Response::Error { message, .. } => Err(RemoteError::Agent {
+                message,
+                transient: false,
+                fatal: true,
+            }
+            .into()),
+            response => Err(RemoteError::Protocol(format!("{:?}", response)).into()),
+        }
+    }
+}
+
+impl Connection {
+    fn request(&mut self, request: &Request) -> Result<Response> {
+        send(self.stream.get_mut(), request).map_err(RemoteError::Connection)?;
+
+        match receive(&mut self.stream).map_err(RemoteError::Connection)? {
+            Some(response) => Ok(response),
+            None => Err(RemoteError::Connection(io::ErrorKind::UnexpectedEof.into()).into()),
+        }
+    }
+}
+
+impl StateSource for RemoteGame {
+    fn state(&self) -> Result<State> {
+        RemoteGame::state(self)
+    }
+}
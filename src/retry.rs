@@ -0,0 +1,61 @@
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::Result;
+
+// Applied to each individual read; only transient errors are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    // Doubled after every failed attempt
+    pub backoff: Duration,
+    // Upper bound of a random delay added to each backoff
+    pub jitter: Duration,
+}
+
+impl RetryPolicy {
+    pub(crate) fn run<T>(&self, mut read: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+
+        loop {
+            match read() {
+                Err(e) if e.is_transient() && attempt < self.attempts => {
+                    tracing::trace!("attempt {} failed, retrying: {}", attempt, e);
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1));
+
+        let jitter_nanos = self.jitter.as_nanos() as u64;
+        let jitter = if jitter_nanos == 0 {
+            0
+        } else {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| u64::from(now.subsec_nanos()) % jitter_nanos)
+                .unwrap_or(0)
+        };
+
+        backoff + Duration::from_nanos(jitter)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 1,
+            backoff: Duration::from_millis(5),
+            jitter: Duration::from_millis(0),
+        }
+    }
+}
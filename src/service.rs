@@ -0,0 +1,325 @@
+// Runs the capture side unattended, as a Windows service: waits for the game,
+// serves the agent and metrics while it's running, and goes back to waiting
+// when it closes. A failed or panicking cycle is started over, and the service
+// is installed to be restarted by Windows if the process itself dies.
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceAction, ServiceActionType, ServiceControl, ServiceControlAccept,
+        ServiceErrorControl, ServiceExitCode, ServiceFailureActions, ServiceFailureResetPeriod,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+use crate::{
+    game::Game, redact::Redaction, remote::Agent, watcher::GameWatcher, watcher::StopHandle,
+};
+
+#[cfg(feature = "prometheus")]
+use crate::prometheus::Exporter;
+
+pub const SERVICE_NAME: &str = "TaskinatorCommunicator";
+const DISPLAY_NAME: &str = "Taskinator Communicator";
+
+// Between looking for the game, and after a cycle fails
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+// How long Windows waits before restarting a service that died
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+// After a day without failures Windows forgets about the earlier ones
+const FAILURE_RESET: Duration = Duration::from_secs(24 * 60 * 60);
+
+// What to serve; with nothing set it only polls, which is still a health check
+#[derive(Debug, Clone)]
+pub struct Runner {
+    agent: Option<String>,
+    token: Option<String>,
+    redaction: Redaction,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<String>,
+    interval: Duration,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner {
+            agent: None,
+            token: None,
+            redaction: Redaction::default(),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            interval: Duration::from_millis(250),
+        }
+    }
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Serve an Agent here while the game's running
+    pub fn agent(mut self, addr: impl Into<String>) -> Self {
+        self.agent = Some(addr.into());
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    // Serve GET /metrics here for as long as the runner runs
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, addr: impl Into<String>) -> Self {
+        self.metrics = Some(addr.into());
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // Blocks until stopped; fails only if the metrics address can't be bound
+    pub fn run(&self, stop: &StopHandle) -> io::Result<()> {
+        #[cfg(feature = "prometheus")]
+        let exporter = match &self.metrics {
+            Some(addr) => {
+                let exporter = Exporter::new();
+                exporter.serve(addr.as_str())?;
+                Some(exporter)
+            }
+            None => None,
+        };
+
+        while !stop.is_stopped() {
+            let cycle = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.cycle(
+                    stop,
+                    #[cfg(feature = "prometheus")]
+                    exporter.clone(),
+                )
+            }));
+
+            match cycle {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("runner cycle failed: {}", e),
+                Err(_) => tracing::error!("runner cycle panicked; starting over"),
+            }
+
+            sleep_unless_stopped(RETRY_INTERVAL, stop);
+        }
+
+        Ok(())
+    }
+
+    // Once through: find the game, serve it until it closes or stops reading
+    fn cycle(
+        &self,
+        stop: &StopHandle,
+        #[cfg(feature = "prometheus")] exporter: Option<Exporter>,
+    ) -> crate::Result<()> {
+        let game = loop {
+            if stop.is_stopped() {
+                return Ok(());
+            }
+
+            match Game::find() {
+                Ok(game) => break game,
+                Err(e) => tracing::trace!("no game to attach to yet: {}", e),
+            }
+
+            sleep_unless_stopped(RETRY_INTERVAL, stop);
+        };
+        tracing::info!("attached to the game, pid {}", game.pid());
+
+        // The agent gets a handle of its own, so it's never held up by polls
+        let agent_stop = StopHandle::default();
+        let agent = match &self.agent {
+            Some(addr) => {
+                let mut agent =
+                    Agent::new(Game::from_pid(game.pid())?).redaction(self.redaction.clone());
+                if let Some(token) = &self.token {
+                    agent = agent.token(token.as_str());
+                }
+
+                match agent.serve_until(addr.as_str(), agent_stop.clone()) {
+                    Ok(thread) => Some(thread),
+                    Err(e) => {
+                        tracing::warn!("couldn't serve the agent on {}: {}", addr, e);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let mut watcher = GameWatcher::new(game);
+        #[cfg(feature = "prometheus")]
+        if let Some(exporter) = exporter {
+            watcher = watcher.exporter(exporter);
+        }
+
+        let result = loop {
+            if stop.is_stopped() || !watcher.game().is_running() {
+                break Ok(());
+            }
+
+            match watcher.poll() {
+                Err(e) if e.is_fatal() => break Err(e),
+                Err(e) => tracing::trace!("poll failed: {}", e),
+                Ok(_) => {}
+            }
+
+            thread::sleep(self.interval);
+        };
+
+        agent_stop.stop();
+        if let Some(agent) = agent {
+            let _ = agent.join();
+        }
+        tracing::info!("detached from the game");
+
+        result
+    }
+}
+
+fn sleep_unless_stopped(duration: Duration, stop: &StopHandle) {
+    const STEP: Duration = Duration::from_millis(100);
+
+    let mut slept = Duration::ZERO;
+    while slept < duration && !stop.is_stopped() {
+        thread::sleep(STEP);
+        slept += STEP;
+    }
+}
+
+// Installs the running executable as a service started at boot, which Windows
+// launches with these arguments; needs an elevated prompt
+pub fn install(arguments: Vec<OsString>) -> windows_service::Result<()> {
+    let manager =
+        ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CREATE_SERVICE)?;
+
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe().map_err(windows_service::Error::Winapi)?,
+        launch_arguments: arguments,
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+
+    let service =
+        manager.create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)?;
+    service.set_description("Reads Among Us for overlays and bots")?;
+    service.update_failure_actions(ServiceFailureActions {
+        reset_period: ServiceFailureResetPeriod::After(FAILURE_RESET),
+        reboot_msg: None,
+        command: None,
+        actions: Some(vec![
+            ServiceAction {
+                action_type: ServiceActionType::Restart,
+                delay: RESTART_DELAY,
+            };
+            3
+        ]),
+    })?;
+    service.set_failure_actions_on_non_crash_failures(true)?;
+
+    service.start::<&OsStr>(&[])
+}
+
+// Stops the service if it's running, then removes it
+pub fn uninstall() -> windows_service::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)?;
+    let service = manager.open_service(
+        SERVICE_NAME,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+    )?;
+
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop()?;
+    }
+
+    service.delete()
+}
+
+static RUNNER: OnceLock<Runner> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+// For the process Windows starts: hands control to the service dispatcher,
+// returning once the service has stopped
+pub fn run_as_service(runner: Runner) -> windows_service::Result<()> {
+    let _ = RUNNER.set(runner);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+}
+
+fn service_main(_: Vec<OsString>) {
+    if let Err(e) = serve_service() {
+        tracing::error!("service failed: {}", e);
+    }
+}
+
+fn serve_service() -> windows_service::Result<()> {
+    let stop = StopHandle::default();
+
+    let handler_stop = stop.clone();
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                handler_stop.stop();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+
+    let status = |current_state, exit_code| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted: match current_state {
+            ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            _ => ServiceControlAccept::empty(),
+        },
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    };
+
+    status_handle.set_service_status(status(ServiceState::Running, 0))?;
+
+    let runner = RUNNER.get().cloned().unwrap_or_default();
+    let exit_code = match runner.run(&stop) {
+        Ok(()) => 0,
+        Err(e) => {
+            tracing::error!("runner failed: {}", e);
+            // Reported as a failure, so Windows restarts the service
+            e.raw_os_error().unwrap_or(1) as u32
+        }
+    };
+
+    status_handle.set_service_status(status(ServiceState::Stopped, exit_code))
+}
@@ -0,0 +1,304 @@
+// Comparing a state against a known good one, for regression fixtures: "this
+// read should produce this State". Only what the game shows is compared, so
+// where players were read from never matters, and players are matched by id
+// whatever order they came in.
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use crate::state::{Player, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    Phase {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    // Expected and actual values, as debug text
+    Field {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+    MissingPlayer(u8),
+    UnexpectedPlayer(u8),
+    Player {
+        id: u8,
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+// Players sorted by id, so equal states compare and serialise the same
+pub fn normalize(state: &State) -> State {
+    let mut state = state.clone();
+    if let State::Lobby { players } | State::InGame { players, .. } | State::Ended { players } =
+        &mut state
+    {
+        players.sort_by_key(Player::key);
+    }
+
+    state
+}
+
+pub fn compare(expected: &State, actual: &State) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    match (expected, actual) {
+        (State::Menu, State::Menu)
+        | (State::Lobby { .. }, State::Lobby { .. })
+        | (State::Ended { .. }, State::Ended { .. }) => {}
+        (
+            State::InGame {
+                meeting,
+                tasks_completed,
+                tasks_total,
+                ..
+            },
+            State::InGame {
+                meeting: actual_meeting,
+                tasks_completed: actual_completed,
+                tasks_total: actual_total,
+                ..
+            },
+        ) => {
+            field(&mut differences, "meeting", meeting, actual_meeting);
+            field(
+                &mut differences,
+                "tasks_completed",
+                tasks_completed,
+                actual_completed,
+            );
+            field(&mut differences, "tasks_total", tasks_total, actual_total);
+        }
+        _ => {
+            differences.push(Difference::Phase {
+                expected: phase(expected),
+                actual: phase(actual),
+            });
+            return differences;
+        }
+    }
+
+    for player in expected.players() {
+        match actual.players().iter().find(|p| p.id == player.id) {
+            Some(actual) => compare_player(&mut differences, player, actual),
+            None => differences.push(Difference::MissingPlayer(player.id)),
+        }
+    }
+
+    for player in actual.players() {
+        if !expected.players().iter().any(|p| p.id == player.id) {
+            differences.push(Difference::UnexpectedPlayer(player.id));
+        }
+    }
+
+    differences
+}
+
+// Panics listing every difference, for use in tests
+pub fn assert_matches(expected: &State, actual: &State) {
+    let differences = compare(expected, actual);
+
+    if !differences.is_empty() {
+        let list: Vec<String> = differences.iter().map(ToString::to_string).collect();
+        panic!("states differ:\n  {}", list.join("\n  "));
+    }
+}
+
+fn compare_player(differences: &mut Vec<Difference>, expected: &Player, actual: &Player) {
+    let id = expected.id;
+    let mut player_field = |field, expected: String, actual: String| {
+        if expected != actual {
+            differences.push(Difference::Player {
+                id,
+                field,
+                expected,
+                actual,
+            });
+        }
+    };
+
+    player_field(
+        "name",
+        format!("{:?}", expected.name),
+        format!("{:?}", actual.name),
+    );
+    player_field(
+        "colour",
+        format!("{:?}", expected.colour),
+        format!("{:?}", actual.colour),
+    );
+    player_field(
+        "hat",
+        format!("{:?}", expected.hat),
+        format!("{:?}", actual.hat),
+    );
+    player_field(
+        "pet",
+        format!("{:?}", expected.pet),
+        format!("{:?}", actual.pet),
+    );
+    player_field(
+        "skin",
+        format!("{:?}", expected.skin),
+        format!("{:?}", actual.skin),
+    );
+    player_field(
+        "disconnected",
+        expected.disconnected.to_string(),
+        actual.disconnected.to_string(),
+    );
+    player_field(
+        "impostor",
+        expected.impostor.to_string(),
+        actual.impostor.to_string(),
+    );
+    player_field("dead", expected.dead.to_string(), actual.dead.to_string());
+    // Sorted, since a HashMap's order changes from run to run
+    player_field(
+        "extra_roles",
+        format!(
+            "{:?}",
+            expected.extra_roles.iter().collect::<BTreeMap<_, _>>()
+        ),
+        format!(
+            "{:?}",
+            actual.extra_roles.iter().collect::<BTreeMap<_, _>>()
+        ),
+    );
+}
+
+fn field<T: PartialEq + std::fmt::Debug>(
+    differences: &mut Vec<Difference>,
+    field: &'static str,
+    expected: &T,
+    actual: &T,
+) {
+    if expected != actual {
+        differences.push(Difference::Field {
+            field,
+            expected: format!("{:?}", expected),
+            actual: format!("{:?}", actual),
+        });
+    }
+}
+
+fn phase(state: &State) -> &'static str {
+    match state {
+        State::Menu => "menu",
+        State::Lobby { .. } => "lobby",
+        State::InGame { .. } => "in game",
+        State::Ended { .. } => "game over",
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Difference::Phase { expected, actual } => {
+                f.write_fmt(format_args!("expected {}, got {}", expected, actual))
+            }
+            Difference::Field {
+                field,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "{}: expected {}, got {}",
+                field, expected, actual
+            )),
+            Difference::MissingPlayer(id) => f.write_fmt(format_args!("player {} is missing", id)),
+            Difference::UnexpectedPlayer(id) => {
+                f.write_fmt(format_args!("player {} wasn't expected", id))
+            }
+            Difference::Player {
+                id,
+                field,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "player {} {}: expected {}, got {}",
+                id, field, expected, actual
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MeetingState;
+
+    #[test]
+    fn normalize_sorts_players() {
+        let state = State::Ended {
+            players: vec![Player::test(2, "Green"), Player::test(0, "Red")],
+        };
+
+        let ids: Vec<u8> = normalize(&state).players().iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn equal_states_in_any_order_match() {
+        let expected = State::Ended {
+            players: vec![Player::test(0, "Red"), Player::test(1, "Blue")],
+        };
+        let actual = State::Ended {
+            players: vec![Player::test(1, "Blue"), Player::test(0, "Red")],
+        };
+
+        assert!(compare(&expected, &actual).is_empty());
+    }
+
+    #[test]
+    fn lists_each_difference() {
+        let expected = State::InGame {
+            meeting: MeetingState::Proceeding,
+            players: vec![Player::test(0, "Red"), Player::test(1, "Blue")],
+            tasks_completed: 3,
+            tasks_total: 10,
+        };
+        let mut dead = Player::test(0, "Red");
+        dead.dead = true;
+        let actual = State::InGame {
+            meeting: MeetingState::Proceeding,
+            players: vec![dead, Player::test(2, "Green")],
+            tasks_completed: 4,
+            tasks_total: 10,
+        };
+
+        let differences = compare(&expected, &actual);
+        assert_eq!(differences.len(), 4);
+        assert!(differences.contains(&Difference::Field {
+            field: "tasks_completed",
+            expected: "3".to_string(),
+            actual: "4".to_string(),
+        }));
+        assert!(differences.contains(&Difference::Player {
+            id: 0,
+            field: "dead",
+            expected: "false".to_string(),
+            actual: "true".to_string(),
+        }));
+        assert!(differences.contains(&Difference::MissingPlayer(1)));
+        assert!(differences.contains(&Difference::UnexpectedPlayer(2)));
+    }
+
+    #[test]
+    fn different_phases_stop_there() {
+        let lobby = State::Lobby {
+            players: vec![Player::test(0, "Red")],
+        };
+
+        assert_eq!(
+            compare(&State::Menu, &lobby),
+            vec![Difference::Phase {
+                expected: "menu",
+                actual: "lobby",
+            }]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+// Keeps games, their players and what happened in them in a SQLite database,
+// for stats that outlast a session. Feed it every poll; games are cut at the
+// transitions into and out of State::InGame.
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+use crate::{events::Event, state::State};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id INTEGER PRIMARY KEY,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS players (
+        game_id INTEGER NOT NULL REFERENCES games (id),
+        player_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        colour INTEGER NOT NULL,
+        impostor INTEGER NOT NULL,
+        dead INTEGER NOT NULL,
+        disconnected INTEGER NOT NULL,
+        PRIMARY KEY (game_id, player_id)
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        game_id INTEGER REFERENCES games (id),
+        at INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        player_id INTEGER,
+        detail TEXT
+    );
+";
+
+// Totals for one name across every recorded game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerTotals {
+    pub name: String,
+    pub games: u32,
+    pub impostor_games: u32,
+    pub deaths: u32,
+}
+
+pub struct StatsDb {
+    connection: Connection,
+    // Row id of the game in progress
+    game: Option<i64>,
+    last: Option<State>,
+}
+
+impl StatsDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(StatsDb {
+            connection,
+            game: None,
+            last: None,
+        })
+    }
+
+    // For queries the helpers below don't cover
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn record(&mut self, state: &State, events: &[Event]) -> Result<()> {
+        let now = unix_millis();
+        let was_in_game = matches!(self.last, Some(State::InGame { .. }));
+        let in_game = matches!(state, State::InGame { .. });
+
+        if in_game && !was_in_game {
+            self.connection
+                .execute("INSERT INTO games (started_at) VALUES (?1)", params![now])?;
+            self.game = Some(self.connection.last_insert_rowid());
+        }
+
+        // The lobby after a game would otherwise overwrite how it ended
+        if let (Some(game), true) = (self.game, in_game) {
+            for player in state.players() {
+                let died = self
+                    .last
+                    .as_ref()
+                    .and_then(|last| last.players().iter().find(|p| p.id == player.id))
+                    .is_some_and(|before| player.dead && !before.dead);
+                if died {
+                    self.insert_event(now, "death", Some(player.id), None)?;
+                }
+
+                self.connection.execute(
+                    "INSERT OR REPLACE INTO players
+                        (game_id, player_id, name, colour, impostor, dead, disconnected)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        game,
+                        player.id,
+                        player.name,
+                        player.colour.id(),
+                        player.impostor,
+                        player.dead,
+                        player.disconnected
+                    ],
+                )?;
+            }
+        }
+
+        for event in events {
+            let (kind, id) = match event {
+                Event::NameChanged { id, .. } => ("name_changed", id),
+                Event::ColourChanged { id, .. } => ("colour_changed", id),
+                Event::HatChanged { id, .. } => ("hat_changed", id),
+                Event::PetChanged { id, .. } => ("pet_changed", id),
+                Event::SkinChanged { id, .. } => ("skin_changed", id),
+            };
+            let detail = serde_json::to_string(event).ok();
+            self.insert_event(now, kind, Some(*id), detail)?;
+        }
+
+        if was_in_game && !in_game {
+            if let Some(game) = self.game.take() {
+                self.connection.execute(
+                    "UPDATE games SET ended_at = ?1 WHERE id = ?2",
+                    params![now, game],
+                )?;
+            }
+        }
+
+        self.last = Some(state.clone());
+
+        Ok(())
+    }
+
+    pub fn games_played(&self) -> Result<u32> {
+        self.connection
+            .query_row("SELECT COUNT(*) FROM games", [], |row| row.get(0))
+    }
+
+    pub fn player_totals(&self, name: &str) -> Result<Option<PlayerTotals>> {
+        self.connection
+            .query_row(
+                "SELECT name, COUNT(*), SUM(impostor), SUM(dead) FROM players
+                 WHERE name = ?1 GROUP BY name",
+                params![name],
+                player_totals,
+            )
+            .optional()
+    }
+
+    // Most games first
+    pub fn leaderboard(&self, limit: u32) -> Result<Vec<PlayerTotals>> {
+        self.connection
+            .prepare(
+                "SELECT name, COUNT(*), SUM(impostor), SUM(dead) FROM players
+                 GROUP BY name ORDER BY COUNT(*) DESC, name LIMIT ?1",
+            )?
+            .query_map(params![limit], player_totals)?
+            .collect()
+    }
+
+    fn insert_event(
+        &self,
+        at: i64,
+        kind: &str,
+        player_id: Option<u8>,
+        detail: Option<String>,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO events (game_id, at, kind, player_id, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![self.game, at, kind, player_id, detail],
+        )?;
+
+        Ok(())
+    }
+}
+
+fn player_totals(row: &rusqlite::Row<'_>) -> Result<PlayerTotals> {
+    Ok(PlayerTotals {
+        name: row.get(0)?,
+        games: row.get(1)?,
+        impostor_games: row.get(2)?,
+        deaths: row.get(3)?,
+    })
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
@@ -0,0 +1,237 @@
+// What a state read produces; nothing here touches the game process, so these
+// work the same on any platform, e.g. for analysing recorded states
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    colour::Colour,
+    cosmetics::{Hat, Pet, Skin},
+    packs::RoleValue,
+};
+
+pub(crate) type GameUSize = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum State {
+    Menu,
+    Lobby {
+        // code: String,
+        players: Vec<Player>,
+    },
+    InGame {
+        // code: String,
+        meeting: MeetingState,
+        players: Vec<Player>,
+        tasks_completed: GameUSize,
+        tasks_total: GameUSize,
+    },
+    // From the game ending until everyone's back in the lobby; the players are
+    // as the game left them, roles and deaths included
+    Ended {
+        players: Vec<Player>,
+    },
+}
+
+// Ordered by how far through the meeting it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MeetingState {
+    Animating,
+    Discussion,
+    NotVoted,
+    Voted,
+    Results,
+    Proceeding,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Player {
+    pub id: u8,
+    pub name: String,
+    pub colour: Colour,
+    pub hat: Hat,
+    pub pet: Pet,
+    pub skin: Skin,
+    pub disconnected: bool,
+    // Where the live reader found the player; absent off Windows
+    #[cfg(windows)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) tasks_addr: GameUSize,
+    pub impostor: bool,
+    pub dead: bool,
+    #[cfg(windows)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) game_object_addr: GameUSize,
+    // Fields read through offset packs, keyed by RoleField::key
+    pub extra_roles: HashMap<String, RoleValue>,
+}
+
+impl Player {
+    // Stays the same for as long as the player is in the lobby, whatever else
+    // about them changes
+    pub fn key(&self) -> u8 {
+        self.id
+    }
+}
+
+// A connected, living crewmate with nothing on, for tests to adjust
+#[cfg(test)]
+impl Player {
+    pub(crate) fn test(id: u8, name: &str) -> Self {
+        Player {
+            id,
+            name: name.to_string(),
+            colour: Colour::from(i32::from(id)),
+            hat: Hat::from(0),
+            pet: Pet::from(0),
+            skin: Skin::from(0),
+            disconnected: false,
+            #[cfg(windows)]
+            tasks_addr: 0,
+            impostor: false,
+            dead: false,
+            #[cfg(windows)]
+            game_object_addr: 0,
+            extra_roles: HashMap::new(),
+        }
+    }
+}
+
+// Compares what's visible about the player, not where it was read from
+impl PartialEq for Player {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.name == other.name
+            && self.colour == other.colour
+            && self.hat == other.hat
+            && self.pet == other.pet
+            && self.skin == other.skin
+            && self.disconnected == other.disconnected
+            && self.impostor == other.impostor
+            && self.dead == other.dead
+            && self.extra_roles == other.extra_roles
+    }
+}
+
+impl Eq for Player {}
+
+// HashMap can't be hashed, so extra roles are left out; equal players still
+// hash the same
+impl Hash for Player {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.name.hash(state);
+        self.colour.hash(state);
+        self.hat.hash(state);
+        self.pet.hash(state);
+        self.skin.hash(state);
+        self.disconnected.hash(state);
+        self.impostor.hash(state);
+        self.dead.hash(state);
+    }
+}
+
+// Which of the three a state is, for checking without reading the rest; see
+// Game::phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    Menu,
+    Lobby,
+    InGame,
+    Ended,
+}
+
+// Anything that hands out states: a live Game, a RemoteGame, a FakeGame and so
+// on, so code built on one can be pointed at another
+pub trait StateSource {
+    fn state(&self) -> crate::Result<State>;
+}
+
+impl State {
+    pub fn phase(&self) -> Phase {
+        match self {
+            State::Menu => Phase::Menu,
+            State::Lobby { .. } => Phase::Lobby,
+            State::InGame { .. } => Phase::InGame,
+            State::Ended { .. } => Phase::Ended,
+        }
+    }
+
+    pub fn players(&self) -> &[Player] {
+        match self {
+            State::Menu => &[],
+            State::Lobby { players } | State::InGame { players, .. } | State::Ended { players } => {
+                players
+            }
+        }
+    }
+}
+
+// One header line, then a row per player:
+//
+//   in game, tasks 12/40, meeting: discussion
+//    0  Alice         Red     alive
+//    3  Bob           Lime    dead  impostor
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            State::Menu => return f.write_str("menu"),
+            State::Lobby { players } => write!(f, "lobby, {} players", players.len())?,
+            State::InGame {
+                meeting,
+                tasks_completed,
+                tasks_total,
+                ..
+            } => write!(
+                f,
+                "in game, tasks {}/{}, meeting: {}",
+                tasks_completed, tasks_total, meeting
+            )?,
+            State::Ended { players } => write!(f, "game over, {} players", players.len())?,
+        }
+
+        for player in self.players() {
+            let colour = player
+                .colour
+                .name()
+                .map(String::from)
+                .unwrap_or_else(|| format!("#{}", player.colour.id()));
+            let status = if player.disconnected {
+                "left"
+            } else if player.dead {
+                "dead"
+            } else {
+                "alive"
+            };
+
+            write!(f, "\n{:>2}  {:<12}  {:<7} ", player.id, player.name, colour)?;
+
+            if player.impostor {
+                write!(f, "{:<5} impostor", status)?;
+            } else {
+                f.write_str(status)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for MeetingState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            MeetingState::Animating => "animating",
+            MeetingState::Discussion => "discussion",
+            MeetingState::NotVoted => "voting",
+            MeetingState::Voted => "voted",
+            MeetingState::Results => "results",
+            MeetingState::Proceeding => "none",
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+// Totals since the game was attached or the stats were last reset
+#[derive(Debug, Clone, Default)]
+pub struct ReadStats {
+    // Individual ReadProcessMemory calls, retries included
+    pub reads: u64,
+    pub bytes: u64,
+    pub failures: u64,
+    // States read again because the game changed while they were being read,
+    // with Consistency::Verified
+    pub torn_states: u64,
+    pub last_state_duration: Option<Duration>,
+}
+
+// A single ReadProcessMemory call, as passed to a read hook
+#[derive(Debug, Clone)]
+pub struct ReadRecord {
+    pub address: usize,
+    pub requested: usize,
+    pub bytes: usize,
+    pub what: &'static str,
+    pub duration: Duration,
+    pub succeeded: bool,
+    // GetLastError for a failed read, e.g. 299 for a partial copy
+    pub error: Option<u32>,
+}
+
+pub type ReadHook = Box<dyn Fn(&ReadRecord) + Send + Sync>;
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    reads: AtomicU64,
+    bytes: AtomicU64,
+    failures: AtomicU64,
+    torn_states: AtomicU64,
+    // 0 until the first state read finishes
+    last_state_nanos: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn record_read(&self, bytes: usize, succeeded: bool) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+
+        if !succeeded {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_torn_state(&self) {
+        self.torn_states.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_state(&self, duration: Duration) {
+        self.last_state_nanos
+            .store(duration.as_nanos().max(1) as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ReadStats {
+        let last_state_nanos = self.last_state_nanos.load(Ordering::Relaxed);
+
+        ReadStats {
+            reads: self.reads.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            torn_states: self.torn_states.load(Ordering::Relaxed),
+            last_state_duration: if last_state_nanos == 0 {
+                None
+            } else {
+                Some(Duration::from_nanos(last_state_nanos))
+            },
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.reads.store(0, Ordering::Relaxed);
+        self.bytes.store(0, Ordering::Relaxed);
+        self.failures.store(0, Ordering::Relaxed);
+        self.torn_states.store(0, Ordering::Relaxed);
+        self.last_state_nanos.store(0, Ordering::Relaxed);
+    }
+}
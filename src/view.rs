@@ -0,0 +1,193 @@
+// States flattened into what an overlay actually draws: strings ready to show,
+// colours as hex and the roster in display order, with a Redaction applied
+// first. Everything is owned, so a view can be handed straight to a UI thread
+// or serialised for a browser source.
+use crate::{
+    metrics,
+    packs::RoleValue,
+    redact::Redaction,
+    state::{MeetingState, Player, State},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct View {
+    // "menu", "lobby", "in_game" or "ended"
+    pub phase: String,
+    // As MeetingState displays; None outside a game or between meetings
+    pub meeting: Option<String>,
+    // e.g. "12/40" and "30%"; None outside a game or before tasks are handed out
+    pub tasks: Option<String>,
+    pub task_percent: Option<String>,
+    // From 0 to 1, for progress bars
+    pub task_progress: Option<f32>,
+    pub alive_crew: usize,
+    pub alive_impostors: usize,
+    // Living players first, then the dead, then those who left; by id within each
+    pub roster: Vec<PlayerView>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerView {
+    pub id: u8,
+    pub name: String,
+    // The game's name for it, or #id for one this crate doesn't know
+    pub colour_name: String,
+    // "#RRGGBB"; None when the colour isn't known
+    pub colour_hex: Option<String>,
+    pub hat: String,
+    pub pet: String,
+    pub skin: String,
+    // "alive", "dead" or "left"
+    pub status: String,
+    pub impostor: bool,
+    pub dead: bool,
+    pub disconnected: bool,
+    // Offset pack fields as display strings, sorted by name
+    pub roles: Vec<(String, String)>,
+}
+
+impl View {
+    pub fn new(state: &State, redaction: &Redaction) -> Self {
+        let state = redaction.redacted(state);
+        let metrics = metrics::metrics(&state);
+
+        let (phase, meeting, tasks) = match &state {
+            State::Menu => ("menu", None, None),
+            State::Lobby { .. } => ("lobby", None, None),
+            State::Ended { .. } => ("ended", None, None),
+            State::InGame {
+                meeting,
+                tasks_completed,
+                tasks_total,
+                ..
+            } => (
+                "in_game",
+                Some(*meeting).filter(|meeting| *meeting != MeetingState::Proceeding),
+                Some(format!("{}/{}", tasks_completed, tasks_total)).filter(|_| *tasks_total != 0),
+            ),
+        };
+
+        let mut roster: Vec<PlayerView> = state.players().iter().map(PlayerView::new).collect();
+        roster.sort_by_key(|player| (player.disconnected, player.dead, player.id));
+
+        View {
+            phase: phase.to_string(),
+            meeting: meeting.map(|meeting| meeting.to_string()),
+            tasks,
+            task_percent: metrics
+                .task_progress
+                .map(|progress| format!("{:.0}%", progress * 100.0)),
+            task_progress: metrics.task_progress,
+            alive_crew: metrics.alive_crew,
+            alive_impostors: metrics.alive_impostors,
+            roster,
+        }
+    }
+}
+
+impl PlayerView {
+    fn new(player: &Player) -> Self {
+        let name_or_id = |name: Option<&str>, id: String| name.map_or(id, String::from);
+
+        let mut roles: Vec<(String, String)> = player
+            .extra_roles
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    RoleValue::Bool(value) => value.to_string(),
+                    RoleValue::Int(value) => value.to_string(),
+                    RoleValue::String(value) => value.clone(),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        roles.sort();
+
+        PlayerView {
+            id: player.id,
+            name: player.name.clone(),
+            colour_name: name_or_id(player.colour.name(), format!("#{}", player.colour.id())),
+            colour_hex: player
+                .colour
+                .as_rgb()
+                .map(|(r, g, b)| format!("#{:02X}{:02X}{:02X}", r, g, b)),
+            hat: name_or_id(player.hat.name(), format!("#{}", player.hat.id())),
+            pet: name_or_id(player.pet.name(), format!("#{}", player.pet.id())),
+            skin: name_or_id(player.skin.name(), format!("#{}", player.skin.id())),
+            status: if player.disconnected {
+                "left"
+            } else if player.dead {
+                "dead"
+            } else {
+                "alive"
+            }
+            .to_string(),
+            impostor: player.impostor,
+            dead: player.dead,
+            disconnected: player.disconnected,
+            roles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cosmetics::Hat;
+
+    #[test]
+    fn flattens_a_game() {
+        let mut impostor = Player::test(0, "Red");
+        impostor.impostor = true;
+        impostor.hat = Hat::from(999);
+        let mut dead = Player::test(1, "Blue");
+        dead.dead = true;
+        let mut left = Player::test(2, "Green");
+        left.disconnected = true;
+        let state = State::InGame {
+            meeting: MeetingState::Discussion,
+            players: vec![left, dead, impostor, Player::test(3, "Pink")],
+            tasks_completed: 3,
+            tasks_total: 12,
+        };
+
+        let view = View::new(&state, &Redaction::default());
+        assert_eq!(view.phase, "in_game");
+        assert_eq!(view.meeting.as_deref(), Some("discussion"));
+        assert_eq!(view.tasks.as_deref(), Some("3/12"));
+        assert_eq!(view.task_percent.as_deref(), Some("25%"));
+        assert_eq!((view.alive_crew, view.alive_impostors), (1, 1));
+
+        let roster: Vec<(u8, &str)> = view
+            .roster
+            .iter()
+            .map(|player| (player.id, player.status.as_str()))
+            .collect();
+        assert_eq!(
+            roster,
+            vec![(0, "alive"), (3, "alive"), (1, "dead"), (2, "left")]
+        );
+
+        let red = &view.roster[0];
+        assert_eq!(red.colour_name, "Red");
+        assert_eq!(red.colour_hex.as_deref(), Some("#C61111"));
+        assert_eq!(red.hat, "#999");
+        assert!(red.impostor);
+    }
+
+    #[test]
+    fn applies_the_redaction() {
+        let mut impostor = Player::test(0, "Red");
+        impostor.impostor = true;
+        let state = State::Lobby {
+            players: vec![impostor],
+        };
+
+        let view = View::new(&state, &Redaction::public());
+        assert_eq!(view.phase, "lobby");
+        assert_eq!(view.tasks, None);
+        assert!(!view.roster[0].impostor);
+    }
+}
@@ -0,0 +1,166 @@
+// Who should be muted or deafened in voice chat for a state, so Discord bots
+// don't each work it out again. Only decides; applying it is up to the bot.
+//
+// Pass states through a LivingView first: muting someone the moment they die
+// tells everyone else there's been a kill.
+use crate::state::{MeetingState, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Voice {
+    pub mute: bool,
+    pub deafen: bool,
+}
+
+impl Voice {
+    pub const OPEN: Voice = Voice {
+        mute: false,
+        deafen: false,
+    };
+    pub const MUTED: Voice = Voice {
+        mute: true,
+        deafen: false,
+    };
+    pub const DEAFENED: Voice = Voice {
+        mute: true,
+        deafen: true,
+    };
+}
+
+// What each kind of player gets at each point of the game. The default is the
+// usual arrangement: while playing, the living hear nothing and the dead talk
+// among themselves; in meetings the living talk and the dead listen
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MutePolicy {
+    // Menu, lobby and the end of game screen
+    pub lobby: Voice,
+    pub tasks_alive: Voice,
+    pub tasks_dead: Voice,
+    pub meeting_alive: Voice,
+    pub meeting_dead: Voice,
+}
+
+impl Default for MutePolicy {
+    fn default() -> Self {
+        MutePolicy {
+            lobby: Voice::OPEN,
+            tasks_alive: Voice::DEAFENED,
+            tasks_dead: Voice::OPEN,
+            meeting_alive: Voice::OPEN,
+            meeting_dead: Voice::MUTED,
+        }
+    }
+}
+
+impl MutePolicy {
+    // For servers where the bot can't deafen: the living are only muted while
+    // playing, so they can still hear the dead
+    pub fn mute_only() -> Self {
+        MutePolicy {
+            tasks_alive: Voice::MUTED,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoiceDecision {
+    pub id: u8,
+    // For matching to voice users; bots usually link the two by name
+    pub name: String,
+    pub voice: Voice,
+}
+
+// One decision per connected player, in the state's order; players who left
+// are skipped, since there's no one in voice to apply it to
+pub fn decide(state: &State, policy: &MutePolicy) -> Vec<VoiceDecision> {
+    let in_meeting = match state {
+        State::InGame { meeting, .. } => Some(*meeting != MeetingState::Proceeding),
+        State::Menu | State::Lobby { .. } | State::Ended { .. } => None,
+    };
+
+    state
+        .players()
+        .iter()
+        .filter(|player| !player.disconnected)
+        .map(|player| VoiceDecision {
+            id: player.id,
+            name: player.name.clone(),
+            voice: match (in_meeting, player.dead) {
+                (None, _) => policy.lobby,
+                (Some(false), false) => policy.tasks_alive,
+                (Some(false), true) => policy.tasks_dead,
+                (Some(true), false) => policy.meeting_alive,
+                (Some(true), true) => policy.meeting_dead,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Player;
+
+    fn in_game(meeting: MeetingState) -> State {
+        let mut dead = Player::test(1, "Blue");
+        dead.dead = true;
+        let mut left = Player::test(2, "Green");
+        left.disconnected = true;
+
+        State::InGame {
+            meeting,
+            players: vec![Player::test(0, "Red"), dead, left],
+            tasks_completed: 0,
+            tasks_total: 10,
+        }
+    }
+
+    fn voices(state: &State, policy: &MutePolicy) -> Vec<(u8, Voice)> {
+        decide(state, policy)
+            .into_iter()
+            .map(|decision| (decision.id, decision.voice))
+            .collect()
+    }
+
+    #[test]
+    fn while_playing() {
+        let state = in_game(MeetingState::Proceeding);
+
+        assert_eq!(
+            voices(&state, &MutePolicy::default()),
+            vec![(0, Voice::DEAFENED), (1, Voice::OPEN)]
+        );
+        assert_eq!(
+            voices(&state, &MutePolicy::mute_only()),
+            vec![(0, Voice::MUTED), (1, Voice::OPEN)]
+        );
+    }
+
+    #[test]
+    fn in_meetings() {
+        assert_eq!(
+            voices(&in_game(MeetingState::Discussion), &MutePolicy::default()),
+            vec![(0, Voice::OPEN), (1, Voice::MUTED)]
+        );
+    }
+
+    #[test]
+    fn outside_a_game() {
+        let state = State::Lobby {
+            players: vec![Player::test(0, "Red")],
+        };
+
+        assert_eq!(
+            decide(&state, &MutePolicy::default()),
+            vec![VoiceDecision {
+                id: 0,
+                name: "Red".to_string(),
+                voice: Voice::OPEN,
+            }]
+        );
+        assert!(decide(&State::Menu, &MutePolicy::default()).is_empty());
+    }
+}
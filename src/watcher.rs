@@ -0,0 +1,325 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender, TrySendError};
+
+use crate::{
+    error::Error,
+    events::{self, Compared, Event},
+    game::Game,
+    state::State,
+    Result,
+};
+
+#[cfg(feature = "prometheus")]
+use crate::prometheus::Exporter;
+
+// Limits on how hard the watcher works, however often poll() is called
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub max_polls_per_second: Option<u32>,
+    // Counted from Game::read_stats, so includes retries
+    pub max_reads_per_second: Option<u32>,
+}
+
+// What spawn_thread does with events when the channel is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // Stop polling until the consumer catches up
+    Block,
+    // Keep polling and discard events that don't fit
+    DropNewest,
+}
+
+// How often a poll waiting on a worker checks whether it's been stopped
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct GameWatcher {
+    // Shared with the worker thread while a poll with a deadline is running
+    game: Arc<Game>,
+    last: Option<State>,
+    // None until set; spawn_thread takes that as on and poll as off
+    auto_reattach: Option<bool>,
+    reattach_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    stop: StopHandle,
+    budget: Budget,
+    last_poll: Option<Instant>,
+    // Start of the current one second window and the read count at that point
+    read_window: Option<(Instant, u64)>,
+    channel_capacity: usize,
+    overflow: Overflow,
+    #[cfg(feature = "prometheus")]
+    exporter: Option<Exporter>,
+}
+
+impl GameWatcher {
+    pub fn new(game: Game) -> Self {
+        GameWatcher {
+            game: Arc::new(game),
+            last: None,
+            auto_reattach: None,
+            reattach_timeout: None,
+            deadline: None,
+            stop: StopHandle::default(),
+            budget: Budget::default(),
+            last_poll: None,
+            read_window: None,
+            channel_capacity: 64,
+            overflow: Overflow::Block,
+            #[cfg(feature = "prometheus")]
+            exporter: None,
+        }
+    }
+
+    // When the game goes away, wait for it to be restarted rather than
+    // returning the error; off for poll unless set, on for spawn_thread
+    pub fn auto_reattach(mut self, enabled: bool, timeout: Option<Duration>) -> Self {
+        self.auto_reattach = Some(enabled);
+        self.reattach_timeout = timeout;
+        self
+    }
+
+    // Reads the state on a worker thread and gives up on it with
+    // Error::Timeout if it isn't back in time
+    pub fn deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    // poll() sleeps as long as it needs to stay within the budget
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    // Applies to the channel created by spawn_thread
+    pub fn channel(mut self, capacity: usize, overflow: Overflow) -> Self {
+        self.channel_capacity = capacity;
+        self.overflow = overflow;
+        self
+    }
+
+    // Polls on a thread of its own until stopped, until the receiver is
+    // dropped, or until an error it can't recover from; other errors are
+    // logged and polling carries on. Re-attaches after the game restarts
+    // unless auto_reattach was turned off.
+    pub fn spawn_thread(mut self, interval: Duration) -> (JoinHandle<()>, Receiver<Event>) {
+        let (sender, receiver) = crossbeam_channel::bounded(self.channel_capacity);
+        self.auto_reattach.get_or_insert(true);
+
+        let handle = thread::spawn(move || loop {
+            match self.poll() {
+                Ok((_, events)) => {
+                    if !self.send_events(&sender, events) {
+                        break;
+                    }
+                }
+                Err(Error::Cancelled) => break,
+                Err(e) if e.is_fatal() => {
+                    tracing::warn!("watcher thread stopping: {}", e);
+                    break;
+                }
+                Err(e) => tracing::debug!("poll failed: {}", e),
+            }
+
+            thread::sleep(interval);
+        });
+
+        (handle, receiver)
+    }
+
+    // false once the receiver has gone or the watcher's been stopped
+    fn send_events(&self, sender: &Sender<Event>, events: Vec<Event>) -> bool {
+        for event in events {
+            let sent = match self.overflow {
+                Overflow::Block => self.send_blocking(sender, event),
+                Overflow::DropNewest => match sender.try_send(event) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(event)) => {
+                        tracing::trace!("event channel full, dropping {:?}", event);
+                        true
+                    }
+                    Err(TrySendError::Disconnected(_)) => false,
+                },
+            };
+
+            if !sent {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Waits for room without missing a stop
+    fn send_blocking(&self, sender: &Sender<Event>, mut event: Event) -> bool {
+        loop {
+            match sender.send_timeout(event, STOP_CHECK_INTERVAL) {
+                Ok(()) => return true,
+                Err(SendTimeoutError::Timeout(_)) if self.stop.is_stopped() => return false,
+                Err(SendTimeoutError::Timeout(unsent)) => event = unsent,
+                Err(SendTimeoutError::Disconnected(_)) => return false,
+            }
+        }
+    }
+
+    // Makes a poll that's waiting, whether on a deadline or to reattach,
+    // return Error::Cancelled; so do any polls after it
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+
+    // For stopping the watcher from another thread while it's polling
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    // Every poll is recorded, including ones that fail
+    #[cfg(feature = "prometheus")]
+    pub fn exporter(mut self, exporter: Exporter) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn poll(&mut self) -> Result<(State, Vec<Event>)> {
+        self.throttle();
+
+        let started = Instant::now();
+        let result = self.poll_state();
+
+        #[cfg(feature = "prometheus")]
+        if let Some(exporter) = &self.exporter {
+            exporter.record_poll(
+                &self.game,
+                started.elapsed(),
+                result.as_ref().map(|(state, _)| state),
+            );
+        }
+        #[cfg(not(feature = "prometheus"))]
+        let _ = started;
+
+        result
+    }
+
+    fn throttle(&mut self) {
+        const WINDOW: Duration = Duration::from_secs(1);
+
+        if let (Some(max_polls), Some(last_poll)) =
+            (self.budget.max_polls_per_second, self.last_poll)
+        {
+            let interval = WINDOW / max_polls.max(1);
+            let elapsed = last_poll.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+
+        if let Some(max_reads) = self.budget.max_reads_per_second {
+            let reads = self.game.read_stats().reads;
+            let (window_start, window_reads) =
+                *self.read_window.get_or_insert((Instant::now(), reads));
+            let elapsed = window_start.elapsed();
+
+            if elapsed >= WINDOW {
+                self.read_window = Some((Instant::now(), reads));
+            } else if reads.saturating_sub(window_reads) >= u64::from(max_reads) {
+                tracing::trace!("read budget spent, waiting {:?}", WINDOW - elapsed);
+                thread::sleep(WINDOW - elapsed);
+                self.read_window = Some((Instant::now(), reads));
+            }
+        }
+
+        self.last_poll = Some(Instant::now());
+    }
+
+    fn poll_state(&mut self) -> Result<(State, Vec<Event>)> {
+        let state = match self.read_state() {
+            Err(e) if e.is_fatal() && self.auto_reattach == Some(true) => {
+                tracing::info!("lost the game ({}), waiting to reattach", e);
+                // A worker left behind by a timed-out poll still holds the game
+                let game = Arc::get_mut(&mut self.game).ok_or(Error::Timeout)?;
+                let stop = &self.stop;
+                game.reattach_unless(self.reattach_timeout, || stop.is_stopped())?;
+                self.last = None;
+                self.read_state()?
+            }
+            result => result?,
+        };
+
+        let events = match &self.last {
+            Some(last) => events::diff_with(
+                last,
+                &state,
+                Compared {
+                    cosmetics: self.game.read_options().cosmetics,
+                },
+            ),
+            None => Vec::new(),
+        };
+
+        self.last = Some(state.clone());
+
+        Ok((state, events))
+    }
+
+    fn read_state(&self) -> Result<State> {
+        if self.stop.is_stopped() {
+            return Err(Error::Cancelled);
+        }
+
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return self.game.state(),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let game = Arc::clone(&self.game);
+        thread::spawn(move || {
+            let _ = sender.send(game.state());
+        });
+
+        let started = Instant::now();
+
+        loop {
+            let remaining = deadline.saturating_sub(started.elapsed());
+
+            match receiver.recv_timeout(remaining.min(STOP_CHECK_INTERVAL)) {
+                Ok(state) => return state,
+                Err(RecvTimeoutError::Timeout) if self.stop.is_stopped() => {
+                    return Err(Error::Cancelled)
+                }
+                Err(RecvTimeoutError::Timeout) if remaining == Duration::from_secs(0) => {
+                    tracing::debug!("state read exceeded its {:?} deadline", deadline);
+                    return Err(Error::Timeout);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::Cancelled),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
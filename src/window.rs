@@ -0,0 +1,142 @@
+use std::mem::{size_of, zeroed};
+
+use winapi::{
+    shared::{
+        minwindef::{BOOL, DWORD, FALSE, LPARAM, TRUE},
+        windef::{HWND, POINT, RECT},
+    },
+    um::winuser::{
+        ClientToScreen, EnumWindows, GetClientRect, GetForegroundWindow, GetMonitorInfoW,
+        GetWindow, GetWindowLongW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW,
+        GetWindowThreadProcessId, IsIconic, IsWindowVisible, MonitorFromWindow, GWL_STYLE,
+        GW_OWNER, MONITORINFO, MONITOR_DEFAULTTONEAREST, WS_CAPTION,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    handle: usize,
+}
+
+// In screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+struct Search {
+    pid: DWORD,
+    found: Option<HWND>,
+}
+
+impl Window {
+    // The game's main window: visible, top-level and unowned
+    pub fn find(pid: usize) -> Option<Self> {
+        let mut search = Search {
+            pid: pid as DWORD,
+            found: None,
+        };
+
+        unsafe { EnumWindows(Some(visit), &mut search as *mut Search as LPARAM) };
+
+        search.found.map(|hwnd| Window {
+            handle: hwnd as usize,
+        })
+    }
+
+    pub fn handle(&self) -> usize {
+        self.handle
+    }
+
+    pub fn title(&self) -> Option<String> {
+        let hwnd = self.handle as HWND;
+
+        let len = unsafe { GetWindowTextLengthW(hwnd) };
+        if len <= 0 {
+            return None;
+        }
+
+        let mut title: Vec<u16> = vec![0; len as usize + 1];
+        let copied = unsafe { GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32) };
+        title.truncate(copied.max(0) as usize);
+
+        Some(String::from_utf16_lossy(&title))
+    }
+
+    pub fn is_foreground(&self) -> bool {
+        unsafe { GetForegroundWindow() as usize == self.handle }
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        unsafe { IsIconic(self.handle as HWND) != 0 }
+    }
+
+    // Where the game draws, without the title bar and borders; None once the
+    // window has closed
+    pub fn client_rect(&self) -> Option<Rect> {
+        let hwnd = self.handle as HWND;
+
+        let mut rect: RECT = unsafe { zeroed() };
+        if unsafe { GetClientRect(hwnd, &mut rect) } == 0 {
+            return None;
+        }
+
+        let mut origin = POINT { x: 0, y: 0 };
+        if unsafe { ClientToScreen(hwnd, &mut origin) } == 0 {
+            return None;
+        }
+
+        Some(Rect {
+            x: origin.x,
+            y: origin.y,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+        })
+    }
+
+    // Borderless and covering its whole monitor, which is how Unity's
+    // fullscreen modes look from outside
+    pub fn is_fullscreen(&self) -> bool {
+        let hwnd = self.handle as HWND;
+
+        let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) } as u32;
+        if style & WS_CAPTION == WS_CAPTION {
+            return false;
+        }
+
+        let mut window: RECT = unsafe { zeroed() };
+        if unsafe { GetWindowRect(hwnd, &mut window) } == 0 {
+            return false;
+        }
+
+        let mut monitor: MONITORINFO = unsafe { zeroed() };
+        monitor.cbSize = size_of::<MONITORINFO>() as u32;
+        let handle = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+        if unsafe { GetMonitorInfoW(handle, &mut monitor) } == 0 {
+            return false;
+        }
+
+        let screen = monitor.rcMonitor;
+        window.left <= screen.left
+            && window.top <= screen.top
+            && window.right >= screen.right
+            && window.bottom >= screen.bottom
+    }
+}
+
+unsafe extern "system" fn visit(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let search = &mut *(lparam as *mut Search);
+
+    let mut pid = 0;
+    GetWindowThreadProcessId(hwnd, &mut pid);
+
+    if pid == search.pid && IsWindowVisible(hwnd) != 0 && GetWindow(hwnd, GW_OWNER).is_null() {
+        search.found = Some(hwnd);
+        return FALSE;
+    }
+
+    TRUE
+}
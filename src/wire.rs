@@ -0,0 +1,77 @@
+// Framing shared by the remote agent and the helper pipe: one JSON message per
+// line
+use std::io::{self, BufRead, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+// Longest line receive accepts, so a peer can't grow the buffer without bound
+// by never sending a newline; a full lobby's state is a few KB
+const MAX_LINE: u64 = 1 << 20;
+
+pub(crate) fn send(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    writer.flush()
+}
+
+// None once the other end has closed the connection
+pub(crate) fn receive<T: for<'de> Deserialize<'de>>(
+    reader: &mut impl BufRead,
+) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    let read = reader.by_ref().take(MAX_LINE).read_line(&mut line)?;
+
+    if read == 0 {
+        return Ok(None);
+    }
+
+    if !line.ends_with('\n') && read as u64 == MAX_LINE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too long",
+        ));
+    }
+
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_messages() {
+        let mut buffer = Vec::new();
+        send(&mut buffer, &vec![1, 2, 3]).unwrap();
+        send(&mut buffer, &"second").unwrap();
+
+        let mut reader = &buffer[..];
+        assert_eq!(
+            receive::<Vec<u8>>(&mut reader).unwrap(),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(
+            receive::<String>(&mut reader).unwrap(),
+            Some("second".to_string())
+        );
+        assert_eq!(receive::<String>(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn accepts_a_last_line_without_a_newline() {
+        let mut reader = &b"[4]"[..];
+        assert_eq!(receive::<Vec<u8>>(&mut reader).unwrap(), Some(vec![4]));
+    }
+
+    #[test]
+    fn rejects_lines_over_the_limit() {
+        let line = vec![b'1'; MAX_LINE as usize + 1];
+        let mut reader = BufReader::new(&line[..]);
+
+        let error = receive::<u64>(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}